@@ -0,0 +1,45 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+
+// Every field of the struct is inserted into the entity as a bundle of its own
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = &field.ident;
+                    quote!(self.#ident)
+                })
+                .collect::<Vec<_>>(),
+            Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                .map(|index| {
+                    let index = Index::from(index);
+                    quote!(self.#index)
+                })
+                .collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "Bundle can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    quote! {
+        impl #impl_generics ::core::bundle::Bundle for #name #ty_generics #where_clause {
+            fn insert(self, storage: &mut ::core::Storage, entity: ::core::Entity) {
+                #(::core::bundle::Bundle::insert(#fields, storage, entity);)*
+            }
+        }
+    }
+    .into()
+}
@@ -1,6 +1,15 @@
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
-use crate::scheduler::{IntoSystem, Scheduler, SystemParam};
+use crate::assets::update_assets;
+use crate::input::{update_input, Input, InputBackend};
+use crate::scene::{Scene, SceneError};
+use crate::scheduler::{
+    system::{SystemId, SystemOutput},
+    ErrorHandler, IntoSystem, Scheduler, SystemParam,
+};
+use crate::sub_app::{SubApp, UpdateMode};
+use crate::tasks::poll_tasks;
+use crate::time::{update_time, Time, TimePlugin};
 use crate::{Plugin, Storage};
 use macroquad::prelude::*;
 
@@ -8,58 +17,177 @@ pub struct App {
     pub(crate) storage: Storage,
     pub(crate) scheduler: Scheduler,
     pub(crate) config: Config,
+    pub(crate) sub_apps: Vec<(String, SubApp)>,
 }
 
 impl App {
     pub fn new(config: Config) -> Self {
-        let scheduler: Scheduler = Scheduler::new();
-        let storage: Storage = Storage::new();
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+
+        scheduler.add_plugin::<TimePlugin>();
+        storage.add(Time::default()).add(Input::default());
 
         Self {
             scheduler,
             config,
             storage,
+            sub_apps: Vec::new(),
         }
     }
 
     pub fn add_system<F: IntoSystem<Params> + 'static, Params: SystemParam>(
         &mut self,
         system: F,
-    ) -> &mut Self {
-        self.scheduler.add_system(system);
-        self
+    ) -> SystemId {
+        self.scheduler.add_system(system)
     }
 
     pub fn add_startup_system<F: IntoSystem<Params> + 'static, Params: SystemParam>(
         &mut self,
         system: F,
-    ) -> &mut Self {
-        self.scheduler.add_startup_system(system);
-        self
+    ) -> SystemId {
+        self.scheduler.add_startup_system(system)
     }
 
-    pub fn add_interval_system<F, Params>(
-        &mut self,
-        system: F,
-        call_interval: Duration,
-    ) -> &mut Self
+    pub fn add_interval_system<F, Params>(&mut self, system: F, call_interval: Duration) -> SystemId
     where
         F: IntoSystem<Params> + 'static,
         Params: SystemParam,
     {
-        self.scheduler.add_interval_system(system, call_interval);
-        self
+        self.scheduler.add_interval_system(system, call_interval)
+    }
+
+    pub fn add_exclusive_system<F, Out>(&mut self, system: F) -> SystemId
+    where
+        F: FnMut(&mut Storage) -> Out + Send + 'static,
+        Out: SystemOutput,
+    {
+        self.scheduler.add_exclusive_system(system)
     }
 
     pub fn add_plugin<P: Plugin>(&mut self) -> &mut Self {
         self.scheduler.add_plugin::<P>();
+        // Unique policies apply to components added before the first update
+        self.scheduler.flush_registry(&mut self.storage);
+        self
+    }
+
+    pub fn set_error_handler(&mut self, error_handler: ErrorHandler) -> &mut Self {
+        self.scheduler.set_error_handler(error_handler);
+        self
+    }
+
+    pub fn add_sub_app<L: Into<String>>(&mut self, label: L, sub_app: SubApp) -> &mut Self {
+        let label: String = label.into();
+
+        if self.sub_app(&label).is_some() {
+            panic!("Sub-app `{label}` already exists");
+        }
+
+        self.sub_apps.push((label, sub_app));
+        self
+    }
+
+    pub fn sub_app(&self, label: &str) -> Option<&SubApp> {
+        self.sub_apps
+            .iter()
+            .find(|(name, _)| name == label)
+            .map(|(_, sub_app)| sub_app)
+    }
+
+    pub fn sub_app_mut(&mut self, label: &str) -> Option<&mut SubApp> {
+        self.sub_apps
+            .iter_mut()
+            .find(|(name, _)| name == label)
+            .map(|(_, sub_app)| sub_app)
+    }
+
+    pub fn remove_sub_app(&mut self, label: &str) -> Option<SubApp> {
+        let index: usize = self.sub_apps.iter().position(|(name, _)| name == label)?;
+
+        Some(self.sub_apps.remove(index).1)
+    }
+
+    /// Extracts from the main world into the sub-app and steps it once
+    pub fn update_sub_app(&mut self, label: &str) -> &mut Self {
+        if let Some((_, sub_app)) = self.sub_apps.iter_mut().find(|(name, _)| name == label) {
+            sub_app.extract(&mut self.storage).update();
+        }
+
+        self
+    }
+
+    /// Steps the main world, then every sub-app updated with it
+    pub fn update(&mut self) -> &mut Self {
+        update_time(&mut self.storage);
+        update_input(&mut self.storage);
+        poll_tasks(&mut self.storage);
+        update_assets(&mut self.storage);
+        self.scheduler.run(&mut self.storage);
+
+        self.sub_apps
+            .iter_mut()
+            .filter(|(_, sub_app)| sub_app.update_mode == UpdateMode::WithApp)
+            .for_each(|(_, sub_app)| {
+                sub_app.extract(&mut self.storage).update();
+            });
+
+        self
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut Storage {
+        // Types registered through `scheduler_mut` too
+        self.scheduler.flush_registry(&mut self.storage);
+        &mut self.storage
+    }
+
+    /// Reads the input from the backend instead of the window
+    pub fn set_input_backend<B: InputBackend>(&mut self, backend: B) -> &mut Self {
+        match self.storage.get_first_mut::<Input>() {
+            Some(input) => {
+                input.set_backend(backend);
+            }
+            None => {
+                self.storage.add(Input::new(backend));
+            }
+        }
+
         self
     }
 
+    pub fn scheduler_mut(&mut self) -> &mut Scheduler {
+        &mut self.scheduler
+    }
+
+    /// Only the main world stops the app, a failed sub-app stops alone
+    pub fn is_stopped(&self) -> bool {
+        self.scheduler.is_stopped()
+    }
+
+    /// Nothing is spawned if the scene can't be read or any of its entities is invalid
+    pub fn load_scene<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, SceneError> {
+        self.scheduler.flush_registry(&mut self.storage);
+
+        Scene::load(path)?.spawn(&mut self.storage)?;
+
+        Ok(self)
+    }
+
+    pub fn save_scene<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SceneError> {
+        self.scheduler.flush_registry(&mut self.storage);
+
+        Scene::dump(&self.storage)?.save(path)
+    }
+
     pub async fn run(&mut self) {
-        loop {
+        while !self.is_stopped() {
             clear_background(self.config.background_color);
-            self.scheduler.run(&mut self.storage);
+            self.update();
             next_frame().await;
         }
     }
@@ -69,3 +197,72 @@ impl App {
 pub struct Config {
     pub background_color: Color,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{App, Config};
+    use crate::{storage::Component, Plugin, PluginBuilder, UniquePolicy};
+    use macroquad::prelude::BLACK;
+    use std::any::Any;
+
+    #[derive(Debug)]
+    struct Score(u32);
+
+    impl Plugin for Score {
+        fn new(builder: &mut PluginBuilder) {
+            builder.register_unique::<Score>(UniquePolicy::Error);
+        }
+    }
+
+    impl Component for Score {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn app() -> App {
+        App::new(Config {
+            background_color: BLACK,
+        })
+    }
+
+    #[test]
+    fn unique_policy_applies_before_the_first_update() {
+        let mut app: App = app();
+        app.add_plugin::<Score>();
+
+        let owner = app.storage_mut().try_add(Score(0)).unwrap();
+        let err = app.storage_mut().try_add(Score(1)).unwrap_err();
+
+        assert_eq!(err.owner, owner);
+        assert_eq!(app.storage().count::<Score>(), 1);
+    }
+
+    #[test]
+    fn unique_policy_registered_on_the_scheduler() {
+        let mut app: App = app();
+        app.scheduler_mut()
+            .register_unique::<Score>(UniquePolicy::Replace);
+
+        app.storage_mut().add(Score(0)).add(Score(1));
+
+        assert_eq!(app.storage().count::<Score>(), 1);
+        assert_eq!(
+            app.storage().get_first::<Score>().map(|score| score.0),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn missing_scene_is_reported() {
+        let mut app: App = app();
+        let entities: usize = app.storage().entities().count();
+
+        assert!(app.load_scene("scenes/missing.scn").is_err());
+        assert_eq!(app.storage().entities().count(), entities);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    fmt,
+    future::Future,
+    pin::pin,
+    task::{Context, Poll},
+};
+
+use macroquad::{
+    audio::{load_sound_from_bytes, Sound},
+    texture::{Image, Texture2D},
+};
+
+use crate::{scheduler::system::BoxedError, tasks::noop_waker};
+
+pub type ErasedAsset = Box<dyn Any + Send>;
+
+// Turns the bytes of a file into an asset, called on the main thread
+pub trait AssetLoader: Send + Sync + 'static {
+    type Asset: Send + 'static;
+
+    fn extensions(&self) -> &[&str];
+
+    fn load(&self, bytes: &[u8]) -> Result<Self::Asset, BoxedError>;
+}
+
+pub trait ErasedLoader: Send + Sync + 'static {
+    fn extensions(&self) -> &[&str];
+
+    fn asset_type(&self) -> TypeId;
+
+    fn asset_name(&self) -> &'static str;
+
+    fn load_erased(&self, bytes: &[u8]) -> Result<ErasedAsset, BoxedError>;
+}
+
+impl<L: AssetLoader> ErasedLoader for L {
+    fn extensions(&self) -> &[&str] {
+        AssetLoader::extensions(self)
+    }
+
+    fn asset_type(&self) -> TypeId {
+        TypeId::of::<L::Asset>()
+    }
+
+    fn asset_name(&self) -> &'static str {
+        type_name::<L::Asset>()
+    }
+
+    fn load_erased(&self, bytes: &[u8]) -> Result<ErasedAsset, BoxedError> {
+        Ok(Box::new(self.load(bytes)?))
+    }
+}
+
+impl fmt::Debug for dyn ErasedLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loader")
+            .field("asset", &self.asset_name())
+            .field("extensions", &self.extensions())
+            .finish()
+    }
+}
+
+// Built-in loaders
+
+pub struct BytesLoader;
+
+// Fonts can't be kept in the storage, build them from the bytes with `load_ttf_font_from_bytes`
+impl AssetLoader for BytesLoader {
+    type Asset = Vec<u8>;
+
+    fn extensions(&self) -> &[&str] {
+        &["bin", "ttf", "otf"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<Vec<u8>, BoxedError> {
+        Ok(bytes.to_vec())
+    }
+}
+
+pub struct TextLoader;
+
+// Level files, scenes, configs
+impl AssetLoader for TextLoader {
+    type Asset = String;
+
+    fn extensions(&self) -> &[&str] {
+        &["txt", "json", "scn", "lvl"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<String, BoxedError> {
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+}
+
+pub struct ImageLoader;
+
+impl AssetLoader for ImageLoader {
+    type Asset = Image;
+
+    fn extensions(&self) -> &[&str] {
+        &["png", "jpg", "jpeg", "bmp"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<Image, BoxedError> {
+        // Decoding panics on invalid data
+        std::panic::catch_unwind(|| Image::from_file_with_format(bytes, None))
+            .map_err(|_| "invalid image".into())
+    }
+}
+
+pub struct TextureLoader;
+
+impl AssetLoader for TextureLoader {
+    type Asset = Texture2D;
+
+    fn extensions(&self) -> &[&str] {
+        AssetLoader::extensions(&ImageLoader)
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<Texture2D, BoxedError> {
+        Ok(Texture2D::from_image(&AssetLoader::load(
+            &ImageLoader,
+            bytes,
+        )?))
+    }
+}
+
+pub struct SoundLoader;
+
+impl AssetLoader for SoundLoader {
+    type Asset = Sound;
+
+    fn extensions(&self) -> &[&str] {
+        &["wav", "ogg"]
+    }
+
+    fn load(&self, bytes: &[u8]) -> Result<Sound, BoxedError> {
+        // Sounds are ready right away everywhere except wasm
+        let waker = noop_waker();
+
+        match pin!(load_sound_from_bytes(bytes)).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(sound) => Ok(sound?),
+            Poll::Pending => Err("sound is not ready".into()),
+        }
+    }
+}
@@ -0,0 +1,406 @@
+pub mod handle;
+pub mod loader;
+
+pub use handle::{AssetId, Handle};
+pub use loader::{AssetLoader, BytesLoader, ImageLoader, SoundLoader, TextLoader, TextureLoader};
+
+use std::{
+    any::{type_name, TypeId},
+    collections::HashMap,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Weak,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{registry::Registry, Storage};
+use loader::{ErasedAsset, ErasedLoader};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetEvent {
+    Loaded(AssetId),
+    // Reloaded after the file has changed
+    Modified(AssetId),
+    Failed(AssetId),
+    // No handles are left, the asset is unloaded
+    Removed(AssetId),
+}
+
+struct Entry {
+    path: PathBuf,
+    // None when no loader fits the path and type, the asset never loads
+    loader: Option<Arc<dyn ErasedLoader>>,
+    state: LoadState,
+    asset: Option<ErasedAsset>,
+    refs: Weak<()>,
+    modified: Option<SystemTime>,
+    reading: bool,
+}
+
+// Bytes read on a worker thread
+type Read = (AssetId, std::io::Result<Vec<u8>>);
+
+// Cache of assets loaded from disk
+pub struct Assets {
+    loaders: Vec<Arc<dyn ErasedLoader>>,
+    entries: HashMap<AssetId, Entry>,
+    paths: HashMap<PathBuf, AssetId>,
+    sender: Sender<Read>,
+    receiver: Receiver<Read>,
+    // Sent with the next update
+    events: Vec<AssetEvent>,
+    next_id: u64,
+    watch_interval: Option<Duration>,
+    last_watch: Instant,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            loaders: vec![
+                Arc::new(BytesLoader),
+                Arc::new(TextLoader),
+                Arc::new(ImageLoader),
+                Arc::new(TextureLoader),
+                Arc::new(SoundLoader),
+            ],
+            entries: HashMap::new(),
+            paths: HashMap::new(),
+            sender,
+            receiver,
+            events: Vec::new(),
+            next_id: 0,
+            watch_interval: None,
+            last_watch: Instant::now(),
+        }
+    }
+
+    /// Check files of loaded assets for changes and reload them
+    pub fn watch_for_changes(&mut self, interval: Duration) -> &mut Self {
+        self.watch_interval = Some(interval);
+        self
+    }
+
+    pub fn load<T: Send + 'static, P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        registry: &Registry,
+    ) -> Handle<T> {
+        let path: PathBuf = path.as_ref().to_path_buf();
+
+        // Cached while there are handles
+        if let Some(entry) = self.paths.get(&path).and_then(|id| self.entries.get(id)) {
+            if let Some(refs) = entry.refs.upgrade() {
+                return Handle::new(self.paths[&path], refs);
+            }
+        }
+
+        let loader: Option<Arc<dyn ErasedLoader>> = self.find_loader::<T>(&path, registry);
+        let id: AssetId = AssetId(self.next_id);
+        let refs: Arc<()> = Arc::new(());
+
+        self.next_id += 1;
+
+        let state: LoadState = match loader {
+            Some(_) => {
+                self.paths.insert(path.clone(), id);
+                self.read(id, path.clone());
+                LoadState::Loading
+            }
+            // Not cached, a loader may be registered later
+            None => {
+                self.events.push(AssetEvent::Failed(id));
+                LoadState::Failed(format!(
+                    "no loader of {} for {}",
+                    type_name::<T>(),
+                    path.display()
+                ))
+            }
+        };
+
+        self.entries.insert(
+            id,
+            Entry {
+                modified: modified(&path),
+                reading: loader.is_some(),
+                path,
+                loader,
+                state,
+                asset: None,
+                refs: Arc::downgrade(&refs),
+            },
+        );
+
+        Handle::new(id, refs)
+    }
+
+    pub fn get<T: 'static>(&self, handle: &Handle<T>) -> Option<&T> {
+        self.entries
+            .get(&handle.id())?
+            .asset
+            .as_ref()?
+            .downcast_ref::<T>()
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        self.entries
+            .get_mut(&handle.id())?
+            .asset
+            .as_mut()?
+            .downcast_mut::<T>()
+    }
+
+    pub fn load_state<T>(&self, handle: &Handle<T>) -> LoadState {
+        match self.entries.get(&handle.id()) {
+            Some(entry) => entry.state.clone(),
+            None => LoadState::Failed("unknown asset".to_string()),
+        }
+    }
+
+    pub fn is_loaded<T>(&self, handle: &Handle<T>) -> bool {
+        self.load_state(handle) == LoadState::Loaded
+    }
+
+    pub fn path(&self, id: AssetId) -> Option<&Path> {
+        self.entries.get(&id).map(|entry| entry.path.as_path())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Registered loaders take priority over the built-in ones
+    fn find_loader<T: 'static>(
+        &self,
+        path: &Path,
+        registry: &Registry,
+    ) -> Option<Arc<dyn ErasedLoader>> {
+        let extension: &str = path.extension()?.to_str()?;
+
+        registry
+            .loaders()
+            .chain(self.loaders.iter())
+            .find(|loader| {
+                loader.asset_type() == TypeId::of::<T>() && loader.extensions().contains(&extension)
+            })
+            .cloned()
+    }
+
+    fn read(&self, id: AssetId, path: PathBuf) {
+        let sender: Sender<Read> = self.sender.clone();
+
+        thread::spawn(move || {
+            // The receiver lives as long as the storage
+            let _ = sender.send((id, fs::read(path)));
+        });
+    }
+
+    /// Finishes loading, unloads unused assets and reloads changed files
+    pub fn update(&mut self) -> Vec<AssetEvent> {
+        let mut events: Vec<AssetEvent> = mem::take(&mut self.events);
+
+        // Decode on the main thread, textures and sounds need its context
+        while let Ok((id, bytes)) = self.receiver.try_recv() {
+            let Some(entry) = self.entries.get_mut(&id) else {
+                continue;
+            };
+            let Some(loader) = entry.loader.clone() else {
+                continue;
+            };
+
+            let reload: bool = entry.asset.is_some();
+            entry.reading = false;
+
+            match bytes
+                .map_err(Into::into)
+                .and_then(|bytes| loader.load_erased(&bytes))
+            {
+                Ok(asset) => {
+                    entry.asset = Some(asset);
+                    entry.state = LoadState::Loaded;
+                    events.push(match reload {
+                        true => AssetEvent::Modified(id),
+                        false => AssetEvent::Loaded(id),
+                    });
+                }
+                Err(err) => {
+                    entry.state = LoadState::Failed(err.to_string());
+                    events.push(AssetEvent::Failed(id));
+                }
+            }
+        }
+
+        // Unload assets without handles
+        let unused: Vec<AssetId> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.refs.strong_count() == 0)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in unused {
+            if let Some(entry) = self.entries.remove(&id) {
+                // A newer handle may have loaded the path again
+                if self.paths.get(&entry.path) == Some(&id) {
+                    self.paths.remove(&entry.path);
+                }
+
+                events.push(AssetEvent::Removed(id));
+            }
+        }
+
+        // Hot reload
+        if let Some(interval) = self.watch_interval {
+            if self.last_watch.elapsed() >= interval {
+                self.last_watch = Instant::now();
+                self.reload_modified();
+            }
+        }
+
+        events
+    }
+
+    fn reload_modified(&mut self) {
+        let mut changed: Vec<(AssetId, PathBuf)> = Vec::new();
+
+        for (id, entry) in self.entries.iter_mut() {
+            let modified: Option<SystemTime> = modified(&entry.path);
+
+            if entry.loader.is_some() && !entry.reading && modified != entry.modified {
+                entry.modified = modified;
+                entry.reading = true;
+                changed.push((*id, entry.path.clone()));
+            }
+        }
+
+        for (id, path) in changed {
+            self.read(id, path);
+        }
+    }
+}
+
+impl Default for Assets {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage {
+    pub fn load<T: Send + 'static, P: AsRef<Path>>(&mut self, path: P) -> Handle<T> {
+        self.assets.load(path, &self.registry)
+    }
+
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+
+    pub fn assets_mut(&mut self) -> &mut Assets {
+        &mut self.assets
+    }
+}
+
+pub(crate) fn update_assets(storage: &mut Storage) {
+    for event in storage.assets.update() {
+        storage.send_event(event);
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssetEvent, AssetId, Assets, Handle, LoadState};
+    use crate::registry::Registry;
+    use std::{fs, path::PathBuf, thread, time::Duration};
+
+    fn file(name: &str, contents: &str) -> PathBuf {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("assets-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // Updates until the reading threads are done
+    fn wait_loaded<T>(assets: &mut Assets, handle: &Handle<T>) {
+        for _ in 0..200 {
+            assets.update();
+
+            if assets.load_state(handle) != LoadState::Loading {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn reloading_a_dropped_path_keeps_the_new_handle() {
+        let mut assets: Assets = Assets::new();
+        let registry: Registry = Registry::new();
+        let path: PathBuf = file("reload.txt", "level");
+
+        let old: Handle<String> = assets.load(&path, &registry);
+        let old_id = old.id();
+        drop(old);
+
+        let new: Handle<String> = assets.load(&path, &registry);
+        assert_ne!(new.id(), old_id);
+
+        assert!(assets.update().contains(&AssetEvent::Removed(old_id)));
+        wait_loaded(&mut assets, &new);
+
+        assert_eq!(assets.get(&new).map(String::as_str), Some("level"));
+        // Still cached under the path
+        assert_eq!(assets.load::<String, _>(&path, &registry), new);
+        assert_eq!(assets.len(), 1);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_loader_fails_instead_of_panicking() {
+        let mut assets: Assets = Assets::new();
+        let registry: Registry = Registry::new();
+
+        let unknown: Handle<String> = assets.load("level.unknown", &registry);
+        let wrong_type: Handle<u32> = assets.load("level.txt", &registry);
+
+        assert!(matches!(assets.load_state(&unknown), LoadState::Failed(_)));
+        assert!(matches!(
+            assets.load_state(&wrong_type),
+            LoadState::Failed(_)
+        ));
+        assert_eq!(assets.get(&unknown), None);
+
+        let events: Vec<AssetEvent> = assets.update();
+
+        assert!(events.contains(&AssetEvent::Failed(unknown.id())));
+        assert!(events.contains(&AssetEvent::Failed(wrong_type.id())));
+
+        let id: AssetId = unknown.id();
+        drop(unknown);
+
+        assert_eq!(assets.update(), [AssetEvent::Removed(id)]);
+    }
+}
@@ -0,0 +1,33 @@
+use crate::{storage::Component, Entity, Storage};
+
+pub use core_derive::Bundle;
+
+// Group of components spawned together as one entity
+pub trait Bundle: Send + 'static {
+    fn insert(self, storage: &mut Storage, entity: Entity);
+}
+
+impl<T: Component> Bundle for T {
+    fn insert(self, storage: &mut Storage, entity: Entity) {
+        storage.insert(entity, self);
+    }
+}
+
+macro_rules! impl_bundle_for_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Bundle),*> Bundle for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn insert(self, storage: &mut Storage, entity: Entity) {
+                let ($($name,)*) = self;
+                $($name.insert(storage, entity);)*
+            }
+        }
+    };
+}
+
+impl_bundle_for_tuple!(B1);
+impl_bundle_for_tuple!(B1, B2);
+impl_bundle_for_tuple!(B1, B2, B3);
+impl_bundle_for_tuple!(B1, B2, B3, B4);
+impl_bundle_for_tuple!(B1, B2, B3, B4, B5);
+impl_bundle_for_tuple!(B1, B2, B3, B4, B5, B6);
@@ -0,0 +1,77 @@
+use std::{any::Any, fmt::Debug};
+
+use crate::{storage::Component, Storage};
+
+// Queue of events of one type, kept until read
+#[derive(Debug)]
+pub struct Events<T: Debug + Send + 'static> {
+    events: Vec<T>,
+}
+
+impl<T: Debug + Send + 'static> Events<T> {
+    pub fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.events.iter()
+    }
+
+    pub fn drain(&mut self) -> Vec<T> {
+        self.events.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+impl<T: Debug + Send + 'static> Default for Events<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage {
+    pub fn send_event<T: Debug + Send + 'static>(&mut self, event: T) -> &mut Self {
+        match self.get_first_mut::<Events<T>>() {
+            Some(events) => events.send(event),
+            None => {
+                let mut events: Events<T> = Events::new();
+
+                events.send(event);
+                self.add(events);
+            }
+        }
+
+        self
+    }
+
+    /// Takes every pending event of the type
+    pub fn read_events<T: Debug + Send + 'static>(&mut self) -> Vec<T> {
+        match self.get_first_mut::<Events<T>>() {
+            Some(events) => events.drain(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl<T: Debug + Send + 'static> Component for Events<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,184 @@
+use std::any::Any;
+
+use crate::{storage::Component, Entity, Storage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub Entity);
+
+// Ordered list of child entities
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub Vec<Entity>);
+
+impl Storage {
+    pub fn parent(&self, child: Entity) -> Option<Entity> {
+        self.get_component::<Parent>(child).map(|parent| parent.0)
+    }
+
+    pub fn children(&self, parent: Entity) -> &[Entity] {
+        match self.get_component::<Children>(parent) {
+            Some(children) => &children.0,
+            None => &[],
+        }
+    }
+
+    /// Appends the child to the end of the parent's children
+    pub fn add_child(&mut self, parent: Entity, child: Entity) -> &mut Self {
+        let index: usize = self.children(parent).len();
+
+        self.insert_child(parent, index, child)
+    }
+
+    /// Places the child at the index, moving it from its previous parent
+    pub fn insert_child(&mut self, parent: Entity, index: usize, child: Entity) -> &mut Self {
+        if parent == child || self.ancestors(parent).contains(&child) {
+            panic!("Entity {child} can't be a child of its own descendant {parent}");
+        }
+
+        self.remove_from_parent(child);
+
+        match self.get_component_mut::<Children>(parent) {
+            Some(children) => {
+                let index: usize = index.min(children.0.len());
+                children.0.insert(index, child);
+            }
+            None => {
+                self.insert(parent, Children(vec![child]));
+            }
+        }
+
+        self.insert(child, Parent(parent))
+    }
+
+    pub fn remove_from_parent(&mut self, child: Entity) -> &mut Self {
+        let Some(parent) = self.parent(child) else {
+            return self;
+        };
+
+        if let Some(children) = self.get_component_mut::<Children>(parent) {
+            children.0.retain(|entity| *entity != child);
+        }
+
+        self.remove_component::<Parent>(child)
+    }
+
+    /// Every child followed by its own subtree, in order
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut descendants: Vec<Entity> = Vec::new();
+        let mut stack: Vec<Entity> = self.children(entity).iter().rev().copied().collect();
+
+        while let Some(entity) = stack.pop() {
+            descendants.push(entity);
+            stack.extend(self.children(entity).iter().rev());
+        }
+
+        descendants
+    }
+
+    /// From the direct parent up to the root
+    pub fn ancestors(&self, entity: Entity) -> Vec<Entity> {
+        let mut ancestors: Vec<Entity> = Vec::new();
+        let mut current: Entity = entity;
+
+        while let Some(parent) = self.parent(current) {
+            ancestors.push(parent);
+            current = parent;
+        }
+
+        ancestors
+    }
+
+    pub fn despawn_recursive(&mut self, entity: Entity) -> &mut Self {
+        self.remove_from_parent(entity);
+
+        for descendant in self.descendants(entity) {
+            self.despawn(descendant);
+        }
+
+        self.despawn(entity)
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Parent {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Children {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Children, Parent};
+    use crate::{Entity, Storage};
+
+    // root ─ parent ─ child ─ grandchild
+    //               └ sibling
+    fn tree(storage: &mut Storage) -> [Entity; 5] {
+        let [root, parent, child, grandchild, sibling] = [(); 5].map(|_| storage.spawn_empty());
+
+        storage
+            .add_child(root, parent)
+            .add_child(parent, child)
+            .add_child(child, grandchild)
+            .add_child(parent, sibling);
+
+        [root, parent, child, grandchild, sibling]
+    }
+
+    #[test]
+    fn descendants_are_in_order() {
+        let mut storage: Storage = Storage::new();
+        let [root, parent, child, grandchild, sibling] = tree(&mut storage);
+
+        assert_eq!(
+            storage.descendants(root),
+            [parent, child, grandchild, sibling]
+        );
+        assert_eq!(storage.ancestors(grandchild), [child, parent, root]);
+    }
+
+    #[test]
+    fn despawn_recursive_removes_the_subtree() {
+        let mut storage: Storage = Storage::new();
+        let [root, parent, child, grandchild, sibling] = tree(&mut storage);
+
+        storage.despawn_recursive(parent);
+
+        for entity in [parent, child, grandchild, sibling] {
+            assert!(!storage.contains(entity));
+            assert_eq!(storage.get_component::<Parent>(entity), None);
+            assert_eq!(storage.get_component::<Children>(entity), None);
+        }
+
+        assert!(storage.contains(root));
+        assert_eq!(storage.count::<Parent>(), 0);
+    }
+
+    #[test]
+    fn despawn_recursive_detaches_from_the_parent() {
+        let mut storage: Storage = Storage::new();
+        let [root, parent, child, grandchild, sibling] = tree(&mut storage);
+
+        storage.despawn_recursive(child);
+
+        assert!(!storage.contains(child));
+        assert!(!storage.contains(grandchild));
+        assert_eq!(storage.children(parent), [sibling]);
+        assert_eq!(storage.parent(sibling), Some(parent));
+        assert_eq!(storage.descendants(root), [parent, sibling]);
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashSet},
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use macroquad::prelude::{
+    is_key_pressed, is_key_released, is_mouse_button_down, is_mouse_button_pressed,
+    is_mouse_button_released, mouse_position, KeyCode, MouseButton,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{storage::Component, Storage};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Pressed(KeyCode),
+    Released(KeyCode),
+    Pointer(PointerEvent),
+}
+
+// Left mouse button or a touch, in window pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PointerEvent {
+    pub phase: PointerPhase,
+    pub position: (f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerPhase {
+    Pressed,
+    Moved,
+    Released,
+}
+
+// Where the input of an update comes from
+pub trait InputBackend: Debug + Send + 'static {
+    fn poll(&mut self) -> Vec<InputEvent>;
+}
+
+// State of the keyboard and the pointer during the current update
+#[derive(Debug)]
+pub struct Input {
+    pressed: HashSet<KeyCode>,
+    just_pressed: HashSet<KeyCode>,
+    just_released: HashSet<KeyCode>,
+    last_pressed: Option<KeyCode>,
+    pointer: Option<(f32, f32)>,
+    pointer_down: bool,
+    pointer_events: Vec<PointerEvent>,
+    backend: Box<dyn InputBackend>,
+}
+
+impl Input {
+    pub fn new<B: InputBackend>(backend: B) -> Self {
+        Self {
+            pressed: HashSet::new(),
+            just_pressed: HashSet::new(),
+            just_released: HashSet::new(),
+            last_pressed: None,
+            pointer: None,
+            pointer_down: false,
+            pointer_events: Vec::new(),
+            backend: Box::new(backend),
+        }
+    }
+
+    /// Replaces the backend, keys held by the previous one are released
+    pub fn set_backend<B: InputBackend>(&mut self, backend: B) -> &mut Self {
+        *self = Self::new(backend);
+        self
+    }
+
+    pub fn pressed(&self, key: KeyCode) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        self.just_pressed.contains(&key)
+    }
+
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        self.just_released.contains(&key)
+    }
+
+    /// The last key pressed during the update
+    pub fn last_pressed(&self) -> Option<KeyCode> {
+        self.last_pressed
+    }
+
+    /// Last known position of the pointer
+    pub fn pointer(&self) -> Option<(f32, f32)> {
+        self.pointer
+    }
+
+    pub fn pointer_down(&self) -> bool {
+        self.pointer_down
+    }
+
+    /// Pointer events of the update, in order
+    pub fn pointer_events(&self) -> &[PointerEvent] {
+        &self.pointer_events
+    }
+
+    pub fn update(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+        self.last_pressed = None;
+        self.pointer_events.clear();
+
+        for event in self.backend.poll() {
+            self.apply(event);
+        }
+    }
+
+    pub fn apply(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Pressed(key) => {
+                if self.pressed.insert(key) {
+                    self.just_pressed.insert(key);
+                }
+
+                self.last_pressed = Some(key);
+            }
+            InputEvent::Released(key) => {
+                if self.pressed.remove(&key) {
+                    self.just_released.insert(key);
+                }
+            }
+            InputEvent::Pointer(event) => {
+                self.pointer = Some(event.position);
+                self.pointer_down = event.phase != PointerPhase::Released;
+                self.pointer_events.push(event);
+            }
+        }
+    }
+}
+
+impl Default for Input {
+    fn default() -> Self {
+        Self::new(MacroquadInput::default())
+    }
+}
+
+pub(crate) fn update_input(storage: &mut Storage) {
+    match storage.get_first_mut::<Input>() {
+        Some(input) => input.update(),
+        None => {
+            storage.add(Input::default());
+        }
+    }
+}
+
+// Keyboard, mouse and touchscreen of the window
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MacroquadInput {
+    mouse: Option<(f32, f32)>,
+}
+
+impl InputBackend for MacroquadInput {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        let mut events: Vec<InputEvent> = Vec::new();
+
+        for key in KEY_CODES {
+            if is_key_pressed(key) {
+                events.push(InputEvent::Pressed(key));
+            }
+
+            if is_key_released(key) {
+                events.push(InputEvent::Released(key));
+            }
+        }
+
+        // Macroquad reports touches as the left mouse button too
+        let mouse: (f32, f32) = mouse_position();
+        let phase: Option<PointerPhase> = if is_mouse_button_pressed(MouseButton::Left) {
+            Some(PointerPhase::Pressed)
+        } else if is_mouse_button_released(MouseButton::Left) {
+            Some(PointerPhase::Released)
+        } else if is_mouse_button_down(MouseButton::Left) && self.mouse != Some(mouse) {
+            Some(PointerPhase::Moved)
+        } else {
+            None
+        };
+
+        self.mouse = Some(mouse);
+        events.extend(phase.map(|phase| {
+            InputEvent::Pointer(PointerEvent {
+                phase,
+                position: mouse,
+            })
+        }));
+
+        events
+    }
+}
+
+// Input planned tick by tick, one tick per update starting at 0
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedInput {
+    tick: u64,
+    events: BTreeMap<u64, Vec<InputEvent>>,
+}
+
+impl ScriptedInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Presses the key at the tick and releases it at the next one
+    pub fn press(&mut self, tick: u64, key: KeyCode) -> &mut Self {
+        self.hold(tick, key).release(tick + 1, key)
+    }
+
+    pub fn hold(&mut self, tick: u64, key: KeyCode) -> &mut Self {
+        self.event(tick, InputEvent::Pressed(key))
+    }
+
+    pub fn release(&mut self, tick: u64, key: KeyCode) -> &mut Self {
+        self.event(tick, InputEvent::Released(key))
+    }
+
+    /// Presses and releases the pointer at the same place
+    pub fn tap(&mut self, tick: u64, position: (f32, f32)) -> &mut Self {
+        self.pointer(tick, PointerPhase::Pressed, position).pointer(
+            tick,
+            PointerPhase::Released,
+            position,
+        )
+    }
+
+    /// Drags the pointer over three ticks
+    pub fn swipe(&mut self, tick: u64, from: (f32, f32), to: (f32, f32)) -> &mut Self {
+        self.pointer(tick, PointerPhase::Pressed, from)
+            .pointer(tick + 1, PointerPhase::Moved, to)
+            .pointer(tick + 2, PointerPhase::Released, to)
+    }
+
+    pub fn pointer(&mut self, tick: u64, phase: PointerPhase, position: (f32, f32)) -> &mut Self {
+        self.event(tick, InputEvent::Pointer(PointerEvent { phase, position }))
+    }
+
+    pub fn event(&mut self, tick: u64, event: InputEvent) -> &mut Self {
+        self.events.entry(tick).or_default().push(event);
+        self
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.keys().all(|tick| *tick < self.tick)
+    }
+}
+
+impl InputBackend for ScriptedInput {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        let events: Vec<InputEvent> = self.events.remove(&self.tick).unwrap_or_default();
+
+        self.tick += 1;
+        events
+    }
+}
+
+// Events of every tick, shared with whoever saves them
+pub type Recording = Arc<Mutex<Vec<(u64, InputEvent)>>>;
+
+// Passes the events of another backend through and records them
+#[derive(Debug)]
+pub struct RecordingInput<B: InputBackend> {
+    backend: B,
+    tick: u64,
+    recording: Recording,
+}
+
+impl<B: InputBackend> RecordingInput<B> {
+    pub fn new(backend: B, recording: Recording) -> Self {
+        Self {
+            backend,
+            tick: 0,
+            recording,
+        }
+    }
+}
+
+impl<B: InputBackend> InputBackend for RecordingInput<B> {
+    fn poll(&mut self) -> Vec<InputEvent> {
+        let events: Vec<InputEvent> = self.backend.poll();
+
+        if let Ok(mut recording) = self.recording.lock() {
+            recording.extend(events.iter().map(|event| (self.tick, *event)));
+        }
+
+        self.tick += 1;
+        events
+    }
+}
+
+pub const KEY_CODES: [KeyCode; 120] = [
+    KeyCode::Space,
+    KeyCode::Apostrophe,
+    KeyCode::Comma,
+    KeyCode::Minus,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Semicolon,
+    KeyCode::Equal,
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::LeftBracket,
+    KeyCode::Backslash,
+    KeyCode::RightBracket,
+    KeyCode::GraveAccent,
+    KeyCode::World1,
+    KeyCode::World2,
+    KeyCode::Escape,
+    KeyCode::Enter,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Right,
+    KeyCode::Left,
+    KeyCode::Down,
+    KeyCode::Up,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::CapsLock,
+    KeyCode::ScrollLock,
+    KeyCode::NumLock,
+    KeyCode::PrintScreen,
+    KeyCode::Pause,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::F13,
+    KeyCode::F14,
+    KeyCode::F15,
+    KeyCode::F16,
+    KeyCode::F17,
+    KeyCode::F18,
+    KeyCode::F19,
+    KeyCode::F20,
+    KeyCode::F21,
+    KeyCode::F22,
+    KeyCode::F23,
+    KeyCode::F24,
+    KeyCode::F25,
+    KeyCode::Kp0,
+    KeyCode::Kp1,
+    KeyCode::Kp2,
+    KeyCode::Kp3,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp7,
+    KeyCode::Kp8,
+    KeyCode::Kp9,
+    KeyCode::KpDecimal,
+    KeyCode::KpDivide,
+    KeyCode::KpMultiply,
+    KeyCode::KpSubtract,
+    KeyCode::KpAdd,
+    KeyCode::KpEnter,
+    KeyCode::KpEqual,
+    KeyCode::LeftShift,
+    KeyCode::LeftControl,
+    KeyCode::LeftAlt,
+    KeyCode::LeftSuper,
+    KeyCode::RightShift,
+    KeyCode::RightControl,
+    KeyCode::RightAlt,
+    KeyCode::RightSuper,
+    KeyCode::Menu,
+];
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Input {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
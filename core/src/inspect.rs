@@ -0,0 +1,264 @@
+use std::{fmt, mem};
+
+use macroquad::prelude::{draw_rectangle, draw_text, screen_width, Color, KeyCode, BLACK, WHITE};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    scheduler::Local,
+    storage::{Entity, Item, StorageType},
+    Input, Plugin, PluginBuilder, Storage,
+};
+
+pub const OVERLAY_KEY: KeyCode = KeyCode::F3;
+
+const FONT_SIZE: f32 = 16.;
+const LINE_HEIGHT: f32 = 16.;
+const MARGIN: f32 = 8.;
+const BACKGROUND: Color = Color::new(BLACK.r, BLACK.g, BLACK.b, 0.75);
+
+// Snapshot of what the storage holds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageReport {
+    pub entities: usize,
+    // Alive entities without any component
+    pub empty_entities: usize,
+    // Entity ids are never reused, every despawned one leaves a hole
+    pub despawned_entities: usize,
+    // Share of entity ids which belong to despawned entities
+    pub fragmentation: f32,
+    pub bytes: usize,
+    // Biggest columns first
+    pub columns: Vec<ColumnReport>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnReport {
+    pub name: String,
+    pub storage_type: StorageType,
+    pub count: usize,
+    pub capacity: usize,
+    // Components plus the column itself, heap data owned by components isn't counted
+    pub bytes: usize,
+}
+
+impl StorageReport {
+    pub fn component_types(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn components(&self) -> usize {
+        self.columns.iter().map(|column| column.count).sum()
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnReport> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+}
+
+impl fmt::Display for StorageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "entities: {} ({} empty, {} despawned, {:.0}% fragmented)",
+            self.entities,
+            self.empty_entities,
+            self.despawned_entities,
+            self.fragmentation * 100.
+        )?;
+        writeln!(
+            f,
+            "components: {} of {} types, {} bytes",
+            self.components(),
+            self.component_types(),
+            self.bytes
+        )?;
+
+        for column in self.columns.iter() {
+            writeln!(
+                f,
+                "  {}: {} ({} allocated, {:?}), {} bytes",
+                column.name, column.count, column.capacity, column.storage_type, column.bytes
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Storage {
+    pub fn inspect(&self) -> StorageReport {
+        let mut columns: Vec<ColumnReport> = self
+            .columns()
+            .map(|(token, name, components, owners)| ColumnReport {
+                name: name.to_string(),
+                storage_type: self.storage_type_of(token),
+                count: components.len(),
+                capacity: components.capacity(),
+                bytes: components
+                    .iter()
+                    .map(|component| mem::size_of_val(component.as_ref()))
+                    .sum::<usize>()
+                    + components.capacity() * mem::size_of::<Item>()
+                    + owners.capacity() * mem::size_of::<Entity>()
+                    + self.sparse_index(token).map_or(0, |sparse| {
+                        sparse.capacity() * mem::size_of::<(Entity, usize)>()
+                    }),
+            })
+            .collect();
+
+        columns.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+
+        let entities: usize = self.entities().count();
+        let empty_entities: usize = self
+            .entities()
+            .filter(|entity| {
+                !self
+                    .columns()
+                    .any(|(_, _, _, owners)| owners.contains(entity))
+            })
+            .count();
+        let despawned_entities: usize = self.next_entity as usize - entities;
+
+        StorageReport {
+            entities,
+            empty_entities,
+            despawned_entities,
+            fragmentation: match self.next_entity {
+                0 => 0.,
+                ids => despawned_entities as f32 / ids as f32,
+            },
+            bytes: columns.iter().map(|column| column.bytes).sum(),
+            columns,
+        }
+    }
+}
+
+// Draws the storage report over the game, toggled with F3
+pub struct InspectPlugin;
+
+impl Plugin for InspectPlugin {
+    fn new(builder: &mut PluginBuilder) {
+        builder.add_system(draw_overlay);
+    }
+}
+
+pub fn draw_overlay(storage: &mut Storage, visible: &mut Local<bool>) {
+    let toggled: bool = storage
+        .get_first::<Input>()
+        .is_some_and(|input| input.just_pressed(OVERLAY_KEY));
+
+    if toggled {
+        **visible = !**visible;
+    }
+
+    if !**visible {
+        return;
+    }
+
+    let report: String = storage.inspect().to_string();
+    let lines: Vec<&str> = report.lines().collect();
+
+    draw_rectangle(
+        0.,
+        0.,
+        screen_width(),
+        lines.len() as f32 * LINE_HEIGHT + MARGIN * 2.,
+        BACKGROUND,
+    );
+
+    for (index, line) in lines.iter().enumerate() {
+        draw_text(
+            line,
+            MARGIN,
+            MARGIN + (index + 1) as f32 * LINE_HEIGHT - 4.,
+            FONT_SIZE,
+            WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StorageReport;
+    use crate::{
+        storage::{Component, Entity, StorageType},
+        Storage,
+    };
+    use std::any::{type_name, Any};
+
+    #[derive(Debug)]
+    struct Tag;
+
+    impl Component for Tag {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[derive(Debug)]
+    struct Other;
+
+    impl Component for Other {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn empty_storage_reports_nothing() {
+        let report: StorageReport = Storage::new().inspect();
+
+        assert_eq!(report, StorageReport::default());
+    }
+
+    #[test]
+    fn report_counts_entities_and_columns() {
+        let mut storage: Storage = Storage::new();
+        storage.set_storage_type::<Tag>(StorageType::SparseSet);
+
+        let first: Entity = storage.spawn(Tag);
+        storage.insert(first, Other);
+        storage.spawn(Tag);
+        let despawned: Entity = storage.spawn(Tag);
+        storage.spawn_empty();
+        storage.despawn(despawned);
+
+        let report: StorageReport = storage.inspect();
+
+        assert_eq!(report.entities, 3);
+        assert_eq!(report.empty_entities, 1);
+        assert_eq!(report.despawned_entities, 1);
+        assert_eq!(report.fragmentation, 0.25);
+        assert_eq!(report.components(), 3);
+        assert_eq!(report.component_types(), 2);
+
+        let tag = report.column(type_name::<Tag>()).unwrap();
+        assert_eq!(tag.count, 2);
+        assert_eq!(tag.storage_type, StorageType::SparseSet);
+
+        let other = report.column(type_name::<Other>()).unwrap();
+        assert_eq!(other.count, 1);
+        assert_eq!(other.storage_type, StorageType::Table);
+
+        assert!(report
+            .columns
+            .windows(2)
+            .all(|pair| pair[0].bytes >= pair[1].bytes));
+        assert_eq!(
+            report.bytes,
+            report
+                .columns
+                .iter()
+                .map(|column| column.bytes)
+                .sum::<usize>()
+        );
+    }
+}
@@ -1,9 +1,36 @@
 #![feature(map_many_mut)]
 
 pub mod app;
+pub mod assets;
+pub mod bundle;
+pub mod event;
+pub mod hierarchy;
+pub mod input;
+pub mod inspect;
+pub mod log;
+pub mod registry;
+pub mod scene;
 pub mod scheduler;
 pub mod storage;
+pub mod sub_app;
+pub mod tasks;
+pub mod time;
 
 pub use app::{App, Config};
-pub use scheduler::{schedule, system, Plugin, PluginBuilder, Scheduler};
-pub use storage::Storage;
+pub use assets::{AssetEvent, Assets, Handle};
+pub use bundle::Bundle;
+pub use event::Events;
+pub use hierarchy::{Children, Parent};
+pub use input::{
+    Input, InputBackend, InputEvent, MacroquadInput, PointerEvent, PointerPhase, Recording,
+    RecordingInput, ScriptedInput,
+};
+pub use inspect::{InspectPlugin, StorageReport};
+pub use log::LogPlugin;
+pub use registry::Registry;
+pub use scene::Scene;
+pub use scheduler::{error, schedule, system, Plugin, PluginBuilder, Scheduler};
+pub use storage::{Entity, Storage, StorageType, UniquePolicy};
+pub use sub_app::{SubApp, UpdateMode};
+pub use tasks::TaskPool;
+pub use time::{Stopwatch, Time, Timer, TimerMode};
@@ -0,0 +1,76 @@
+use std::{env, path::PathBuf};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::{Plugin, PluginBuilder};
+
+// Directives like "info" or "core=debug,game=trace"
+pub const FILTER_ENV: &str = "RUST_LOG";
+// Directory of the rotating log files, logs go to stderr when unset
+pub const FILE_ENV: &str = "LOG_FILE";
+
+const DEFAULT_FILTER: &str = "info";
+const FILE_PREFIX: &str = "app.log";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogOutput {
+    Stderr,
+    // A new file every day
+    File { directory: PathBuf, prefix: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogSettings {
+    pub filter: String,
+    pub output: LogOutput,
+}
+
+impl LogSettings {
+    pub fn from_env() -> Self {
+        let filter: String = env::var(FILTER_ENV).unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+        let output: LogOutput = match env::var_os(FILE_ENV) {
+            Some(directory) => LogOutput::File {
+                directory: directory.into(),
+                prefix: FILE_PREFIX.to_string(),
+            },
+            None => LogOutput::Stderr,
+        };
+
+        Self { filter, output }
+    }
+
+    /// Installs the global subscriber, only the first call has an effect
+    pub fn init(&self) {
+        let filter: EnvFilter = EnvFilter::try_new(&self.filter).unwrap_or_else(|err| {
+            eprintln!("Invalid log filter `{}`: {err}", self.filter);
+            EnvFilter::new(DEFAULT_FILTER)
+        });
+        let builder = tracing_subscriber::fmt().with_env_filter(filter);
+
+        let _ = match &self.output {
+            LogOutput::Stderr => builder.with_writer(std::io::stderr).try_init(),
+            LogOutput::File { directory, prefix } => builder
+                .with_ansi(false)
+                .with_writer(tracing_appender::rolling::daily(directory, prefix))
+                .try_init(),
+        };
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            filter: DEFAULT_FILTER.to_string(),
+            output: LogOutput::Stderr,
+        }
+    }
+}
+
+// Prints the scheduler's spans and events, configured by the environment
+pub struct LogPlugin;
+
+impl Plugin for LogPlugin {
+    fn new(_builder: &mut PluginBuilder) {
+        LogSettings::from_env().init();
+    }
+}
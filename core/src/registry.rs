@@ -0,0 +1,169 @@
+use std::{any::type_name, collections::HashMap, fmt, sync::Arc};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    assets::{loader::ErasedLoader, AssetLoader},
+    bundle::Bundle,
+    storage::{
+        component_as_type, Component, Identification, Item, StorageType, Token, UniquePolicy,
+    },
+    Entity, Storage,
+};
+
+pub type Deserializer = fn(Value) -> serde_json::Result<Item>;
+pub type Serializer = fn(&Item) -> serde_json::Result<Value>;
+pub type Inserter = Arc<dyn Fn(&mut Storage, Entity) + Send + Sync>;
+
+// Type information about components which can be read from and written to scenes
+#[derive(Debug, Clone, Copy)]
+pub struct Registration {
+    pub name: &'static str,
+    pub token: Token,
+    pub deserialize: Deserializer,
+    pub serialize: Serializer,
+}
+
+// Named bundle which can be spawned from code, scenes or the console
+#[derive(Clone)]
+pub struct Prefab {
+    pub name: String,
+    insert: Inserter,
+}
+
+impl Prefab {
+    pub fn insert(&self, storage: &mut Storage, entity: Entity) {
+        (self.insert)(storage, entity);
+    }
+}
+
+impl fmt::Debug for Prefab {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prefab").field("name", &self.name).finish()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Registry {
+    components: HashMap<&'static str, Registration>,
+    prefabs: HashMap<String, Prefab>,
+    storage_types: HashMap<Token, StorageType>,
+    uniques: HashMap<Token, UniquePolicy>,
+    loaders: Vec<Arc<dyn ErasedLoader>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T>(&mut self) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        let name: &'static str = short_type_name::<T>();
+
+        self.components.insert(
+            name,
+            Registration {
+                name,
+                token: Self::type_to_token::<T>(),
+                deserialize: deserialize::<T>,
+                serialize: serialize::<T>,
+            },
+        );
+
+        self
+    }
+
+    /// Every component type is stored in a table unless registered otherwise
+    pub fn register_storage<T: Component>(&mut self, storage_type: StorageType) -> &mut Self {
+        self.storage_types
+            .insert(Self::type_to_token::<T>(), storage_type);
+        self
+    }
+
+    pub fn storage_types(&self) -> impl Iterator<Item = (Token, StorageType)> + '_ {
+        self.storage_types
+            .iter()
+            .map(|(token, storage_type)| (*token, *storage_type))
+    }
+
+    /// Only one instance of the component may exist
+    pub fn register_unique<T: Component>(&mut self, policy: UniquePolicy) -> &mut Self {
+        self.uniques.insert(Self::type_to_token::<T>(), policy);
+        self
+    }
+
+    pub fn uniques(&self) -> impl Iterator<Item = (Token, UniquePolicy)> + '_ {
+        self.uniques.iter().map(|(token, policy)| (*token, *policy))
+    }
+
+    pub fn register_prefab<B, F>(&mut self, name: &str, prefab: F) -> &mut Self
+    where
+        B: Bundle,
+        F: Fn() -> B + Send + Sync + 'static,
+    {
+        self.prefabs.insert(
+            name.to_string(),
+            Prefab {
+                name: name.to_string(),
+                insert: Arc::new(move |storage, entity| prefab().insert(storage, entity)),
+            },
+        );
+
+        self
+    }
+
+    pub fn prefab(&self, name: &str) -> Option<Prefab> {
+        self.prefabs.get(name).cloned()
+    }
+
+    pub fn prefabs(&self) -> impl Iterator<Item = &str> {
+        self.prefabs.keys().map(String::as_str)
+    }
+
+    pub fn register_loader<L: AssetLoader>(&mut self, loader: L) -> &mut Self {
+        self.loaders.push(Arc::new(loader));
+        self
+    }
+
+    pub fn loaders(&self) -> impl Iterator<Item = &Arc<dyn ErasedLoader>> {
+        self.loaders.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Registration> {
+        self.components.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Registration> {
+        self.components.values()
+    }
+
+    pub fn merge(&mut self, other: Registry) {
+        self.components.extend(other.components);
+        self.prefabs.extend(other.prefabs);
+        self.storage_types.extend(other.storage_types);
+        self.uniques.extend(other.uniques);
+        self.loaders.extend(other.loaders);
+    }
+}
+
+impl Identification for Registry {}
+
+fn deserialize<T: Component + DeserializeOwned>(value: Value) -> serde_json::Result<Item> {
+    Ok(Box::new(serde_json::from_value::<T>(value)?))
+}
+
+fn serialize<T: Component + Serialize>(component: &Item) -> serde_json::Result<Value> {
+    serde_json::to_value(component_as_type::<T>(component))
+}
+
+// "game::Position" -> "Position"
+fn short_type_name<T>() -> &'static str {
+    let name: &'static str = type_name::<T>();
+    let path: &'static str = name.split('<').next().unwrap_or(name);
+
+    path.rsplit("::").next().unwrap_or(path)
+}
@@ -0,0 +1,299 @@
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    registry::{Prefab, Registration},
+    storage::{Entity, Item, Token},
+    Storage,
+};
+
+type Components = Vec<(Token, Item)>;
+
+// Initial world state: entities and their components keyed by registered name
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SceneEntity {
+    // Components of the prefab are overridden by the listed ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab: Option<String>,
+    #[serde(default)]
+    pub components: BTreeMap<String, Value>,
+}
+
+impl Scene {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        let data: String = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        let data: String = serde_json::to_string_pretty(self)?;
+
+        Ok(fs::write(path, data)?)
+    }
+
+    /// Spawns every entity of the scene, nothing is spawned if any component is invalid
+    pub fn spawn(&self, storage: &mut Storage) -> Result<Vec<Entity>, SceneError> {
+        let mut entities: Vec<(Option<Prefab>, Components)> =
+            Vec::with_capacity(self.entities.len());
+
+        for entity in self.entities.iter() {
+            let prefab: Option<Prefab> = match &entity.prefab {
+                Some(name) => Some(
+                    storage
+                        .registry()
+                        .prefab(name)
+                        .ok_or_else(|| SceneError::UnknownPrefab(name.clone()))?,
+                ),
+                None => None,
+            };
+
+            let mut components: Components = Vec::with_capacity(entity.components.len());
+
+            for (name, value) in entity.components.iter() {
+                let registration: &Registration = storage
+                    .registry()
+                    .get(name)
+                    .ok_or_else(|| SceneError::UnknownComponent(name.clone()))?;
+
+                components.push((
+                    registration.token,
+                    (registration.deserialize)(value.clone())?,
+                ));
+            }
+
+            entities.push((prefab, components));
+        }
+
+        Ok(entities
+            .into_iter()
+            .map(|(prefab, components)| {
+                let entity: Entity = storage.spawn_empty();
+
+                if let Some(prefab) = prefab {
+                    prefab.insert(storage, entity);
+                }
+
+                components.into_iter().for_each(|(token, item)| {
+                    storage.insert_item(token, entity, item);
+                });
+
+                entity
+            })
+            .collect())
+    }
+
+    /// Collects every registered component of the storage, grouped by entity
+    pub fn dump(storage: &Storage) -> Result<Self, SceneError> {
+        let mut entities: BTreeMap<Entity, SceneEntity> = storage
+            .entities()
+            .map(|entity| (entity, SceneEntity::default()))
+            .collect();
+
+        for registration in storage.registry().iter() {
+            if let Some((components, owners)) = storage.column(registration.token) {
+                for (component, owner) in components.iter().zip(owners.iter()) {
+                    entities.entry(*owner).or_default().components.insert(
+                        registration.name.to_string(),
+                        (registration.serialize)(component)?,
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            // Entities made only of unregistered components can't be restored
+            entities: entities
+                .into_values()
+                .filter(|entity| !entity.components.is_empty())
+                .collect(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnknownComponent(String),
+    UnknownPrefab(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Format(err) => write!(f, "invalid scene: {err}"),
+            SceneError::UnknownComponent(name) => {
+                write!(f, "component `{name}` is not registered")
+            }
+            SceneError::UnknownPrefab(name) => write!(f, "prefab `{name}` is not registered"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(err: serde_json::Error) -> Self {
+        SceneError::Format(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scene, SceneError};
+    use crate::{storage::Component, Entity, Registry, Storage};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::any::Any;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    struct Name(String);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    struct Health(u32);
+
+    impl Component for Name {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    impl Component for Health {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn storage() -> Storage {
+        let mut registry: Registry = Registry::new();
+        registry
+            .register::<Name>()
+            .register::<Health>()
+            .register_prefab("guard", || (Name("guard".to_string()), Health(10)));
+
+        let mut storage: Storage = Storage::new();
+        storage.merge_registry(registry);
+        storage
+    }
+
+    fn scene(entities: Value) -> Scene {
+        serde_json::from_value(json!({ "entities": entities })).unwrap()
+    }
+
+    #[test]
+    fn dumped_scene_spawns_the_same_world() {
+        let mut storage: Storage = storage();
+        storage.spawn((Name("snake".to_string()), Health(3)));
+        storage.spawn(Health(5));
+
+        let text: String = serde_json::to_string(&Scene::dump(&storage).unwrap()).unwrap();
+        let scene: Scene = serde_json::from_str(&text).unwrap();
+
+        let mut restored: Storage = self::storage();
+        let entities: Vec<Entity> = scene.spawn(&mut restored).unwrap();
+
+        assert_eq!(entities.len(), 2);
+        assert_eq!(
+            restored.get_component::<Name>(entities[0]),
+            Some(&Name("snake".to_string()))
+        );
+        assert_eq!(
+            restored.get_component::<Health>(entities[0]),
+            Some(&Health(3))
+        );
+        assert_eq!(restored.get_component::<Name>(entities[1]), None);
+        assert_eq!(
+            restored.get_component::<Health>(entities[1]),
+            Some(&Health(5))
+        );
+        assert_eq!(
+            serde_json::to_value(Scene::dump(&restored).unwrap()).unwrap(),
+            serde_json::from_str::<Value>(&text).unwrap()
+        );
+    }
+
+    #[test]
+    fn listed_components_override_the_prefab() {
+        let mut storage: Storage = storage();
+        let entities: Vec<Entity> = scene(json!([
+            { "prefab": "guard", "components": { "Health": 20 } },
+            { "prefab": "guard" }
+        ]))
+        .spawn(&mut storage)
+        .unwrap();
+
+        assert_eq!(
+            storage.get_component::<Health>(entities[0]),
+            Some(&Health(20))
+        );
+        assert_eq!(
+            storage.get_component::<Health>(entities[1]),
+            Some(&Health(10))
+        );
+        assert_eq!(
+            storage.get_component::<Name>(entities[0]),
+            Some(&Name("guard".to_string()))
+        );
+        assert_eq!(storage.count::<Health>(), 2);
+    }
+
+    #[test]
+    fn nothing_is_spawned_from_an_invalid_scene() {
+        let mut storage: Storage = storage();
+
+        let unknown_component = scene(json!([
+            { "components": { "Health": 1 } },
+            { "components": { "Armor": 2 } }
+        ]))
+        .spawn(&mut storage);
+        let unknown_prefab = scene(json!([
+            { "components": { "Health": 1 } },
+            { "prefab": "dragon" }
+        ]))
+        .spawn(&mut storage);
+        let invalid_value = scene(json!([
+            { "components": { "Health": 1 } },
+            { "components": { "Health": "full" } }
+        ]))
+        .spawn(&mut storage);
+
+        assert!(
+            matches!(unknown_component, Err(SceneError::UnknownComponent(name)) if name == "Armor")
+        );
+        assert!(matches!(unknown_prefab, Err(SceneError::UnknownPrefab(name)) if name == "dragon"));
+        assert!(matches!(invalid_value, Err(SceneError::Format(_))));
+        assert_eq!(storage.entities().count(), 0);
+        assert_eq!(storage.count::<Health>(), 0);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(matches!(
+            Scene::load("scenes/missing.scn"),
+            Err(SceneError::Io(_))
+        ));
+    }
+}
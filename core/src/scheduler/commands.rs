@@ -0,0 +1,45 @@
+use std::mem;
+
+use crate::system::SystemId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerCommand {
+    Enable(SystemId),
+    Disable(SystemId),
+    Remove(SystemId),
+    // Stops the app like an error handler would
+    Stop,
+}
+
+// Changes to the schedule requested by systems, applied at the next sync point
+#[derive(Debug, Default)]
+pub struct Commands {
+    queue: Vec<SchedulerCommand>,
+}
+
+impl Commands {
+    pub fn enable(&mut self, id: SystemId) -> &mut Self {
+        self.push(SchedulerCommand::Enable(id))
+    }
+
+    pub fn disable(&mut self, id: SystemId) -> &mut Self {
+        self.push(SchedulerCommand::Disable(id))
+    }
+
+    pub fn remove(&mut self, id: SystemId) -> &mut Self {
+        self.push(SchedulerCommand::Remove(id))
+    }
+
+    pub fn stop(&mut self) -> &mut Self {
+        self.push(SchedulerCommand::Stop)
+    }
+
+    pub fn push(&mut self, command: SchedulerCommand) -> &mut Self {
+        self.queue.push(command);
+        self
+    }
+
+    pub fn take(&mut self) -> Vec<SchedulerCommand> {
+        mem::take(&mut self.queue)
+    }
+}
@@ -0,0 +1,20 @@
+use crate::system::SystemError;
+
+// What the scheduler does with a failed system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    // Report and keep running
+    Log,
+    // Keep running silently
+    Skip,
+    // Report and never run the system again
+    Disable,
+    // Report and stop the app
+    Stop,
+}
+
+pub type ErrorHandler = fn(&SystemError) -> ErrorAction;
+
+pub fn stop_on_error(_: &SystemError) -> ErrorAction {
+    ErrorAction::Stop
+}
@@ -1,7 +1,11 @@
+pub mod commands;
+pub mod error;
 pub mod plugin;
 pub mod schedule;
 pub mod system;
 
+pub use commands::{Commands, SchedulerCommand};
+pub use error::{ErrorAction, ErrorHandler};
 pub use plugin::{Plugin, PluginBuilder};
 pub use schedule::Scheduler;
-pub use system::{IntoSystem, System, SystemParam};
+pub use system::{IntoSystem, Local, Single, System, SystemError, SystemId, SystemParam};
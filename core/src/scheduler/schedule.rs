@@ -1,55 +1,242 @@
-use std::time::Duration;
+use std::{
+    any::{type_name, Any},
+    mem,
+    panic::{self, AssertUnwindSafe},
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, debug_span, error, info, trace_span, warn};
 
 use crate::{
-    system::{IntoSystem, System, SystemParam},
+    assets::AssetLoader,
+    bundle::Bundle,
+    registry::Registry,
+    storage::{Component, StorageType, UniquePolicy},
+    system::{
+        ExclusiveSystem, IntoSystem, System, SystemError, SystemId, SystemOutput, SystemParam,
+        SystemResult,
+    },
     Plugin, PluginBuilder, Storage,
 };
 
-pub type Systems = Vec<Box<dyn System>>;
+use super::{
+    commands::{Commands, SchedulerCommand},
+    error::{stop_on_error, ErrorAction, ErrorHandler},
+};
+
+pub type Systems = Vec<SystemEntry>;
+
+pub struct SystemEntry {
+    id: SystemId,
+    system: Box<dyn System>,
+    enabled: bool,
+}
+
+impl SystemEntry {
+    pub fn new<S: System>(system: S) -> Self {
+        Self {
+            id: SystemId::next(),
+            system: Box::new(system),
+            enabled: true,
+        }
+    }
+
+    pub fn id(&self) -> SystemId {
+        self.id
+    }
+}
 
 pub struct Scheduler {
     startup_systems: Systems,
     systems: Systems,
+    exclusive_systems: Systems,
+    registry: Registry,
+    error_handler: ErrorHandler,
+    stopped: bool,
+    commands: Commands,
 }
 
 impl Scheduler {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             systems: Vec::new(),
             startup_systems: Vec::new(),
+            exclusive_systems: Vec::new(),
+            registry: Registry::new(),
+            error_handler: stop_on_error,
+            stopped: false,
+            commands: Commands::default(),
+        }
+    }
+
+    // Changes take effect at the next sync point, before the next run
+
+    pub fn enable(&mut self, id: SystemId) -> &mut Self {
+        self.commands.enable(id);
+        self
+    }
+
+    pub fn disable(&mut self, id: SystemId) -> &mut Self {
+        self.commands.disable(id);
+        self
+    }
+
+    pub fn remove(&mut self, id: SystemId) -> &mut Self {
+        self.commands.remove(id);
+        self
+    }
+
+    /// False once the system is removed or a startup system has run
+    pub fn is_enabled(&self, id: SystemId) -> bool {
+        self.entries().any(|entry| entry.enabled && entry.id == id)
+    }
+
+    /// Ids of every registration of the function
+    pub fn ids_of<F: 'static>(&self, _: F) -> Vec<SystemId> {
+        self.entries()
+            .filter(|entry| entry.system.name() == type_name::<F>())
+            .map(SystemEntry::id)
+            .collect()
+    }
+
+    fn entries(&self) -> impl Iterator<Item = &SystemEntry> {
+        self.systems
+            .iter()
+            .chain(self.startup_systems.iter())
+            .chain(self.exclusive_systems.iter())
+    }
+
+    // Sync point: apply commands queued by the scheduler's owner and by systems
+    fn apply_commands(&mut self, storage: &mut Storage) {
+        let mut commands: Vec<SchedulerCommand> = self.commands.take();
+        commands.append(&mut storage.commands.take());
+
+        for command in commands {
+            match command {
+                SchedulerCommand::Enable(id) => self.set_enabled(id, true),
+                SchedulerCommand::Disable(id) => self.set_enabled(id, false),
+                SchedulerCommand::Remove(id) => {
+                    for systems in [
+                        &mut self.systems,
+                        &mut self.startup_systems,
+                        &mut self.exclusive_systems,
+                    ] {
+                        systems.retain(|entry| {
+                            let keep: bool = entry.id != id;
+
+                            if !keep {
+                                info!(system = entry.system.name(), "system removed");
+                            }
+
+                            keep
+                        });
+                    }
+                }
+                SchedulerCommand::Stop => {
+                    info!("app stopped by a command");
+                    self.stopped = true;
+                }
+            }
         }
     }
 
-    pub fn add_system<F, Params>(&mut self, system: F) -> &mut Self
+    fn set_enabled(&mut self, id: SystemId, enabled: bool) {
+        self.systems
+            .iter_mut()
+            .chain(self.startup_systems.iter_mut())
+            .chain(self.exclusive_systems.iter_mut())
+            .filter(|entry| entry.id == id && entry.enabled != enabled)
+            .for_each(|entry| {
+                info!(system = entry.system.name(), enabled, "system toggled");
+                entry.enabled = enabled;
+            });
+    }
+
+    pub fn set_error_handler(&mut self, error_handler: ErrorHandler) -> &mut Self {
+        self.error_handler = error_handler;
+        self
+    }
+
+    // Set once a system failed and the error handler chose to stop
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    // Make component available for scenes
+    pub fn register_component<T>(&mut self) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.registry.register::<T>();
+        self
+    }
+
+    // Choose between table and sparse set storage for the component
+    pub fn register_storage<T: Component>(&mut self, storage_type: StorageType) -> &mut Self {
+        self.registry.register_storage::<T>(storage_type);
+        self
+    }
+
+    // Forbid or replace a second instance of the component
+    pub fn register_unique<T: Component>(&mut self, policy: UniquePolicy) -> &mut Self {
+        self.registry.register_unique::<T>(policy);
+        self
+    }
+
+    pub fn register_prefab<B, F>(&mut self, name: &str, prefab: F) -> &mut Self
+    where
+        B: Bundle,
+        F: Fn() -> B + Send + Sync + 'static,
+    {
+        self.registry.register_prefab(name, prefab);
+        self
+    }
+
+    pub fn add_asset_loader<L: AssetLoader>(&mut self, loader: L) -> &mut Self {
+        self.registry.register_loader(loader);
+        self
+    }
+
+    pub fn add_system<F, Params>(&mut self, system: F) -> SystemId
     where
         F: IntoSystem<Params> + 'static,
         Params: SystemParam,
     {
-        self.systems.push(Box::new(system.into(None)));
-        self
+        Self::push(&mut self.systems, system.into(None))
     }
 
-    pub fn add_startup_system<F, Params>(&mut self, system: F) -> &mut Self
+    pub fn add_startup_system<F, Params>(&mut self, system: F) -> SystemId
     where
         F: IntoSystem<Params> + 'static,
         Params: SystemParam,
     {
-        self.startup_systems.push(Box::new(system.into(None)));
-        self
+        Self::push(&mut self.startup_systems, system.into(None))
     }
 
-    pub fn add_interval_system<F, Params>(
-        &mut self,
-        system: F,
-        call_interval: Duration,
-    ) -> &mut Self
+    pub fn add_interval_system<F, Params>(&mut self, system: F, call_interval: Duration) -> SystemId
     where
         F: IntoSystem<Params> + 'static,
         Params: SystemParam,
     {
-        self.systems
-            .push(Box::new(system.into(Some(call_interval))));
-        self
+        Self::push(&mut self.systems, system.into(Some(call_interval)))
+    }
+
+    /// Runs with the whole world at the sync point, before any other system of the frame
+    pub fn add_exclusive_system<F, Out>(&mut self, system: F) -> SystemId
+    where
+        F: FnMut(&mut Storage) -> Out + Send + 'static,
+        Out: SystemOutput,
+    {
+        Self::push(&mut self.exclusive_systems, ExclusiveSystem::new(system))
+    }
+
+    fn push<S: System>(systems: &mut Systems, system: S) -> SystemId {
+        let entry: SystemEntry = SystemEntry::new(system);
+        let id: SystemId = entry.id;
+
+        systems.push(entry);
+        id
     }
 
     pub fn merge_systems(&mut self, mut systems: Systems) {
@@ -60,28 +247,353 @@ impl Scheduler {
         self.startup_systems.append(&mut systems);
     }
 
+    pub fn merge_exclusive_systems(&mut self, mut systems: Systems) {
+        self.exclusive_systems.append(&mut systems);
+    }
+
     pub fn add_plugin<P: Plugin>(&mut self) -> &mut Self {
         let mut plugin = PluginBuilder::new();
 
         // Fill the instance of plugin
         P::new(&mut plugin);
 
+        debug!(
+            plugin = type_name::<P>(),
+            systems = plugin.systems.len(),
+            startup_systems = plugin.startup_systems.len(),
+            exclusive_systems = plugin.exclusive_systems.len(),
+            "plugin added"
+        );
+
         self.merge_systems(plugin.systems);
         self.merge_startup_systems(plugin.startup_systems);
+        self.merge_exclusive_systems(plugin.exclusive_systems);
+        self.registry.merge(plugin.registry);
 
         self
     }
 
+    // Hand over registered types to the storage
+    pub fn flush_registry(&mut self, storage: &mut Storage) {
+        storage.merge_registry(mem::take(&mut self.registry));
+    }
+
     pub fn run(&mut self, storage: &mut Storage) {
-        Self::run_systems(&mut self.startup_systems, storage);
-        Self::run_systems(&mut self.systems, storage);
+        self.flush_registry(storage);
+        self.apply_commands(storage);
+
+        let handler: ErrorHandler = self.error_handler;
+
+        // Nothing else runs until the exclusive systems are done
+        self.stopped = self.stopped
+            || Self::run_systems("exclusive", &mut self.exclusive_systems, storage, handler);
+
+        // Exclusive systems may change the schedule too
+        self.apply_commands(storage);
+
+        self.stopped = self.stopped
+            || Self::run_systems("startup", &mut self.startup_systems, storage, handler)
+            || Self::run_systems("update", &mut self.systems, storage, handler);
 
         self.startup_systems.clear();
     }
 
-    pub fn run_systems(systems: &mut Systems, storage: &mut Storage) {
-        systems.iter_mut().for_each(|system| {
-            system.run(storage);
+    /// Returns true if the error handler asked to stop the app
+    pub fn run_systems(
+        stage: &'static str,
+        systems: &mut Systems,
+        storage: &mut Storage,
+        error_handler: ErrorHandler,
+    ) -> bool {
+        let _stage = debug_span!("stage", name = stage).entered();
+
+        for entry in systems.iter_mut().filter(|entry| entry.enabled) {
+            let _system = trace_span!("system", name = entry.system.name()).entered();
+
+            let Err(error) = Self::run_system(entry.system.as_mut(), storage) else {
+                continue;
+            };
+
+            let error: SystemError = SystemError {
+                system: entry.system.name(),
+                error,
+            };
+
+            match error_handler(&error) {
+                ErrorAction::Log => error!("{error}"),
+                ErrorAction::Skip => (),
+                ErrorAction::Disable => {
+                    error!("{error}, the system is disabled");
+                    entry.enabled = false;
+                }
+                ErrorAction::Stop => {
+                    warn!("{error}, stopping the app");
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // Panics are reported as errors in debug builds
+    fn run_system(system: &mut dyn System, storage: &mut Storage) -> SystemResult {
+        if cfg!(debug_assertions) {
+            panic::catch_unwind(AssertUnwindSafe(|| system.run(storage)))
+                .unwrap_or_else(|payload| Err(panic_message(payload).into()))
+        } else {
+            system.run(storage)
+        }
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "system panicked".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scheduler;
+    use crate::{
+        error::{ErrorAction, ErrorHandler},
+        storage::Component,
+        system::{SystemError, SystemId},
+        Storage,
+    };
+    use std::any::Any;
+
+    #[derive(Debug, Default)]
+    struct Calls(u32);
+
+    impl Component for Calls {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn call(storage: &mut Storage) {
+        if let Some(calls) = storage.get_first_mut::<Calls>() {
+            calls.0 += 1;
+        }
+    }
+
+    fn calls(storage: &Storage) -> u32 {
+        storage.get_first::<Calls>().map_or(0, |calls| calls.0)
+    }
+
+    // Counted as ten calls, then fails
+    fn fail(storage: &mut Storage) -> Result<(), String> {
+        if let Some(calls) = storage.get_first_mut::<Calls>() {
+            calls.0 += 10;
+        }
+
+        Err("out of food".to_string())
+    }
+
+    // The failing system runs first, the other one after it
+    fn run_failing(handler: ErrorHandler, times: usize) -> (Scheduler, SystemId, Storage) {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls::default());
+
+        scheduler.set_error_handler(handler);
+        let failing: SystemId = scheduler.add_system(fail);
+        scheduler.add_system(call);
+
+        for _ in 0..times {
+            scheduler.run(&mut storage);
+        }
+
+        (scheduler, failing, storage)
+    }
+
+    #[test]
+    fn every_registration_has_its_own_id() {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls::default());
+
+        let first: SystemId = scheduler.add_system(call);
+        let second: SystemId = scheduler.add_system(call);
+
+        assert_ne!(first, second);
+        assert_eq!(scheduler.ids_of(call), [first, second]);
+
+        scheduler.disable(first);
+        scheduler.run(&mut storage);
+
+        assert!(!scheduler.is_enabled(first));
+        assert!(scheduler.is_enabled(second));
+        assert_eq!(calls(&storage), 1);
+
+        scheduler.enable(first).remove(second);
+        scheduler.run(&mut storage);
+
+        assert_eq!(scheduler.ids_of(call), [first]);
+        assert_eq!(calls(&storage), 2);
+    }
+
+    #[test]
+    fn startup_and_exclusive_systems_can_be_toggled() {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls::default());
+
+        let startup: SystemId = scheduler.add_startup_system(call);
+        let exclusive: SystemId = scheduler.add_exclusive_system(call);
+
+        assert!(scheduler.is_enabled(startup));
+        assert!(scheduler.is_enabled(exclusive));
+
+        scheduler.disable(exclusive);
+        scheduler.run(&mut storage);
+
+        assert!(!scheduler.is_enabled(exclusive));
+        // Startup systems are gone after the first run
+        assert!(!scheduler.is_enabled(startup));
+        assert_eq!(calls(&storage), 1);
+    }
+
+    #[test]
+    fn plugin_systems_keep_unique_ids() {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let own: SystemId = scheduler.add_system(call);
+
+        scheduler.add_plugin::<crate::time::TimePlugin>();
+
+        let ids: Vec<SystemId> = scheduler.ids_of(crate::time::tick_timers);
+
+        assert_eq!(ids.len(), 1);
+        assert_ne!(ids[0], own);
+    }
+
+    #[test]
+    fn logged_and_skipped_errors_keep_the_system_running() {
+        let handlers: [ErrorHandler; 2] = [|_| ErrorAction::Log, |_| ErrorAction::Skip];
+
+        for handler in handlers {
+            let (scheduler, failing, storage) = run_failing(handler, 2);
+
+            assert!(!scheduler.is_stopped());
+            assert!(scheduler.is_enabled(failing));
+            assert_eq!(calls(&storage), 22);
+        }
+    }
+
+    #[test]
+    fn disabled_system_is_not_run_again() {
+        let (scheduler, failing, storage) = run_failing(|_| ErrorAction::Disable, 3);
+
+        assert!(!scheduler.is_stopped());
+        assert!(!scheduler.is_enabled(failing));
+        assert_eq!(calls(&storage), 13);
+    }
+
+    #[test]
+    fn stop_skips_the_rest_of_the_schedule() {
+        let (scheduler, _, storage) = run_failing(|_| ErrorAction::Stop, 3);
+
+        assert!(scheduler.is_stopped());
+        assert_eq!(calls(&storage), 10);
+    }
+
+    #[test]
+    fn error_names_the_system() {
+        fn handler(error: &SystemError) -> ErrorAction {
+            match error.system.ends_with("::fail") && error.error.to_string() == "out of food" {
+                true => ErrorAction::Skip,
+                false => ErrorAction::Stop,
+            }
+        }
+
+        let (scheduler, _, _) = run_failing(handler, 1);
+
+        assert!(!scheduler.is_stopped());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn panicking_system_is_isolated() {
+        fn explode(_: &mut Storage) {
+            panic!("snake bit itself");
+        }
+
+        // Only the panic is disabled, anything else stops
+        fn handler(error: &SystemError) -> ErrorAction {
+            match error.error.to_string() == "snake bit itself" {
+                true => ErrorAction::Disable,
+                false => ErrorAction::Stop,
+            }
+        }
+
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls::default());
+
+        scheduler.set_error_handler(handler);
+        let exploding: SystemId = scheduler.add_system(explode);
+        scheduler.add_system(call);
+
+        scheduler.run(&mut storage);
+        scheduler.run(&mut storage);
+
+        assert!(!scheduler.is_stopped());
+        assert!(!scheduler.is_enabled(exploding));
+        assert_eq!(calls(&storage), 2);
+    }
+
+    #[test]
+    fn exclusive_systems_run_before_the_update_stage() {
+        fn double(storage: &mut Storage) {
+            if let Some(calls) = storage.get_first_mut::<Calls>() {
+                calls.0 *= 2;
+            }
+        }
+
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls(1));
+
+        // Registered last, run first
+        scheduler.add_system(call);
+        scheduler.add_exclusive_system(double);
+        scheduler.run(&mut storage);
+
+        assert_eq!(calls(&storage), 3);
+    }
+
+    #[test]
+    fn exclusive_system_commands_apply_in_the_same_run() {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+        storage.add(Calls::default());
+
+        let update: SystemId = scheduler.add_system(call);
+        scheduler.add_exclusive_system(move |storage: &mut Storage| {
+            // Every other run
+            match calls(storage) % 2 {
+                0 => storage.commands().enable(update),
+                _ => storage.commands().disable(update),
+            };
         });
+
+        scheduler.run(&mut storage);
+
+        assert_eq!(calls(&storage), 1);
+
+        scheduler.run(&mut storage);
+
+        assert!(!scheduler.is_enabled(update));
+        assert_eq!(calls(&storage), 1);
     }
 }
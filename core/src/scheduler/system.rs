@@ -1,9 +1,18 @@
-use crate::Storage;
+use crate::storage::Component;
+use crate::{Storage, Time};
 use std::{
+    any::type_name,
+    error::Error,
+    fmt,
     marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, SystemTime},
 };
 
+pub type BoxedError = Box<dyn Error + Send + Sync>;
+pub type SystemResult = Result<(), BoxedError>;
+
 // Transforming function into system
 
 pub trait IntoSystem<Params: SystemParam>: Send + 'static {
@@ -22,6 +31,7 @@ where
         FunctionSystem {
             system: self,
             params: PhantomData,
+            state: Default::default(),
             last_call: Duration::ZERO,
             call_interval,
         }
@@ -31,80 +41,465 @@ where
 pub struct FunctionSystem<F: 'static, Params: SystemParam> {
     system: F,
     params: PhantomData<Params>,
+    state: Params::State,
     call_interval: Option<Duration>,
     last_call: Duration,
 }
 
+// One registration of a system, adding the same function twice gives two ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SystemId(u64);
+
+impl SystemId {
+    // Plugins are built on schedulers of their own, one counter keeps the ids unique after merging
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
 // Run system
 
 pub trait System: Send + 'static {
-    fn run(&mut self, storage: &mut Storage);
+    fn name(&self) -> &'static str;
+
+    fn run(&mut self, storage: &mut Storage) -> SystemResult;
 }
 
 impl<F, Params: SystemParam> System for FunctionSystem<F, Params>
 where
     F: SystemParamFunction<Params> + Send + 'static,
 {
-    fn run(&mut self, storage: &mut Storage) {
+    fn name(&self) -> &'static str {
+        type_name::<F>()
+    }
+
+    fn run(&mut self, storage: &mut Storage) -> SystemResult {
         // Run if there is no timer or the time has passed
         if let Some(call_interval) = self.call_interval {
-            match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
-                Ok(now) => {
-                    if self.last_call <= now {
-                        self.last_call = now + call_interval;
-                    } else {
-                        return ();
-                    }
-                }
-                Err(err) => panic!("{}", err),
+            // App time keeps stepped updates deterministic
+            let now: Duration = match storage.get_first::<Time>() {
+                Some(time) => time.elapsed(),
+                None => SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?,
+            };
+
+            if self.last_call <= now {
+                self.last_call = now + call_interval;
+            } else {
+                return Ok(());
             }
         }
 
-        SystemParamFunction::run(&mut self.system, storage);
+        SystemParamFunction::run(&mut self.system, storage, &mut self.state)
+    }
+}
+
+// System with access to the whole world, run alone at the sync point
+pub struct ExclusiveSystem<F: 'static> {
+    system: F,
+}
+
+impl<F> ExclusiveSystem<F> {
+    pub fn new(system: F) -> Self {
+        Self { system }
+    }
+}
+
+impl<F, Out: SystemOutput> System for ExclusiveSystem<F>
+where
+    F: FnMut(&mut Storage) -> Out + Send + 'static,
+{
+    fn name(&self) -> &'static str {
+        type_name::<F>()
+    }
+
+    fn run(&mut self, storage: &mut Storage) -> SystemResult {
+        (self.system)(storage).into_result()
+    }
+}
+
+// Failure of a system, reported to the app's error handler
+#[derive(Debug)]
+pub struct SystemError {
+    pub system: &'static str,
+    pub error: BoxedError,
+}
+
+impl fmt::Display for SystemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "system `{}` failed: {}", self.system, self.error)
+    }
+}
+
+impl Error for SystemError {}
+
+// What systems may return
+pub trait SystemOutput {
+    fn into_result(self) -> SystemResult;
+}
+
+impl SystemOutput for () {
+    fn into_result(self) -> SystemResult {
+        Ok(())
+    }
+}
+
+impl<E: Into<BoxedError>> SystemOutput for Result<(), E> {
+    fn into_result(self) -> SystemResult {
+        self.map_err(Into::into)
     }
 }
 
 // System's params
 pub trait SystemParam: Send + 'static {
-    fn fetch(_: &mut Storage) -> Option<&mut Self> {
-        None
+    // Kept by every registered system between calls
+    type State: Default + Send + 'static;
+
+    // Params borrowing the storage take it, the next one finds it gone
+    fn fetch<'a>(
+        _: &mut Option<&'a mut Storage>,
+        _: &'a mut Self::State,
+    ) -> Result<&'a mut Self, BoxedError> {
+        Err(format!(
+            "`{}` can't be fetched from the storage",
+            type_name::<Self>()
+        )
+        .into())
     }
 }
 
+fn take_storage<'a, P: ?Sized>(
+    storage: &mut Option<&'a mut Storage>,
+) -> Result<&'a mut Storage, BoxedError> {
+    storage.take().ok_or_else(|| {
+        format!(
+            "`{}` needs the storage another param already borrows",
+            type_name::<P>()
+        )
+        .into()
+    })
+}
+
 impl SystemParam for Storage {
-    fn fetch(storage: &mut Storage) -> Option<&mut Storage> {
-        Some(storage)
+    type State = ();
+
+    fn fetch<'a>(
+        storage: &mut Option<&'a mut Storage>,
+        _: &'a mut (),
+    ) -> Result<&'a mut Storage, BoxedError> {
+        take_storage::<Storage>(storage)
+    }
+}
+
+// State of a single system, two registrations of the same function don't share it
+#[derive(Debug, Default)]
+pub struct Local<T: Default + Send + 'static>(T);
+
+impl<T: Default + Send + 'static> SystemParam for Local<T> {
+    type State = Local<T>;
+
+    fn fetch<'a>(
+        _: &mut Option<&'a mut Storage>,
+        state: &'a mut Local<T>,
+    ) -> Result<&'a mut Local<T>, BoxedError> {
+        Ok(state)
+    }
+}
+
+impl<T: Default + Send + 'static> Deref for Local<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Default + Send + 'static> DerefMut for Local<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+// The only component of its type, the system fails when there are none or several
+#[repr(transparent)]
+#[derive(Debug)]
+pub struct Single<T: Component>(T);
+
+impl<T: Component> SystemParam for Single<T> {
+    type State = ();
+
+    fn fetch<'a>(
+        storage: &mut Option<&'a mut Storage>,
+        _: &'a mut (),
+    ) -> Result<&'a mut Self, BoxedError> {
+        let storage: &mut Storage = take_storage::<Self>(storage)?;
+        let count: usize = storage.count::<T>();
+
+        if count != 1 {
+            return Err(format!("expected a single `{}`, found {count}", type_name::<T>()).into());
+        }
+
+        let component: &mut T = storage
+            .get_first_mut::<T>()
+            .ok_or_else(|| format!("`{}` is missing", type_name::<T>()))?;
+
+        // SAFETY: `Single<T>` is a transparent wrapper around `T`
+        Ok(unsafe { &mut *(component as *mut T as *mut Single<T>) })
+    }
+}
+
+impl<T: Component> Deref for Single<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Component> DerefMut for Single<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
     }
 }
 
 // Tuples
 // TODO: Add macro
 
-impl SystemParam for () {}
-impl<T1: SystemParam> SystemParam for (T1,) {}
-impl<T1: SystemParam, T2: SystemParam> SystemParam for (T1, T2) {}
+impl SystemParam for () {
+    type State = ();
+}
+
+impl<T1: SystemParam> SystemParam for (T1,) {
+    type State = T1::State;
+}
+
+impl<T1: SystemParam, T2: SystemParam> SystemParam for (T1, T2) {
+    type State = (T1::State, T2::State);
+}
 
 // Calling functions in systems
 // TODO: Add macro
 
 pub trait SystemParamFunction<Params: SystemParam>: Send + 'static {
-    fn run(&mut self, storage: &mut Storage);
+    fn run(&mut self, storage: &mut Storage, state: &mut Params::State) -> SystemResult;
 }
 
-impl<F> SystemParamFunction<()> for F
+impl<F, Out: SystemOutput> SystemParamFunction<()> for F
 where
-    F: Fn() -> () + Send + 'static,
+    F: Fn() -> Out + Send + 'static,
 {
-    fn run(&mut self, _: &mut Storage) {
-        self();
+    fn run(&mut self, _: &mut Storage, _: &mut ()) -> SystemResult {
+        self().into_result()
     }
 }
 
-impl<F, P1: SystemParam> SystemParamFunction<(P1,)> for F
+impl<F, Out: SystemOutput, P1: SystemParam> SystemParamFunction<(P1,)> for F
 where
-    F: Fn(&mut P1) -> () + Send + 'static,
+    F: Fn(&mut P1) -> Out + Send + 'static,
 {
-    fn run(&mut self, storage: &mut Storage) {
-        self(P1::fetch(storage).unwrap());
+    fn run(&mut self, storage: &mut Storage, state: &mut P1::State) -> SystemResult {
+        self(P1::fetch(&mut Some(storage), state)?).into_result()
+    }
+}
+
+impl<F, Out: SystemOutput, P1: SystemParam, P2: SystemParam> SystemParamFunction<(P1, P2)> for F
+where
+    F: Fn(&mut P1, &mut P2) -> Out + Send + 'static,
+{
+    fn run(&mut self, storage: &mut Storage, state: &mut (P1::State, P2::State)) -> SystemResult {
+        let mut storage: Option<&mut Storage> = Some(storage);
+        let (state1, state2) = state;
+
+        let p1: &mut P1 = P1::fetch(&mut storage, state1)?;
+        let p2: &mut P2 = P2::fetch(&mut storage, state2)?;
+
+        self(p1, p2).into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Local, Single, SystemId, SystemParam};
+    use crate::{error::ErrorAction, storage::Component, Scheduler, Storage};
+    use std::{
+        any::Any,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    #[derive(Debug)]
+    struct Counter {
+        calls: u32,
+    }
+
+    impl Component for Counter {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    // Values seen by the systems, in order
+    #[derive(Debug, Default)]
+    struct Log(Vec<u32>);
+
+    impl Component for Log {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn count(counter: &mut Single<Counter>) {
+        counter.calls += 1;
+    }
+
+    fn log_calls(storage: &mut Storage, calls: &mut Local<u32>) {
+        **calls += 1;
+        storage.get_first_mut::<Log>().unwrap().0.push(**calls);
+    }
+
+    fn log_calls_local_first(calls: &mut Local<u32>, storage: &mut Storage) {
+        **calls += 10;
+        storage.get_first_mut::<Log>().unwrap().0.push(**calls);
+    }
+
+    fn storage_twice(_: &mut Storage, _: &mut Single<Counter>) {}
+
+    fn log_storage() -> Storage {
+        let mut storage: Storage = Storage::new();
+        storage.add(Log::default());
+        storage
+    }
+
+    fn logged(storage: &Storage) -> &[u32] {
+        &storage.get_first::<Log>().unwrap().0
+    }
+
+    #[test]
+    fn single_needs_exactly_one_component() {
+        let mut storage: Storage = Storage::new();
+
+        assert!(Single::<Counter>::fetch(&mut Some(&mut storage), &mut ()).is_err());
+
+        storage.add(Counter { calls: 0 });
+
+        assert!(Single::<Counter>::fetch(&mut Some(&mut storage), &mut ()).is_ok());
+
+        storage.add(Counter { calls: 0 });
+
+        assert!(Single::<Counter>::fetch(&mut Some(&mut storage), &mut ()).is_err());
+    }
+
+    #[test]
+    fn local_only_system_keeps_its_state() {
+        static LAST: AtomicU32 = AtomicU32::new(0);
+
+        fn tick(calls: &mut Local<u32>) {
+            **calls += 1;
+            LAST.store(**calls, Ordering::Relaxed);
+        }
+
+        fn tick_twice(first: &mut Local<u32>, second: &mut Local<u32>) {
+            **first += 1;
+            **second += 2;
+            assert_eq!(**second, **first * 2);
+        }
+
+        let mut storage: Storage = Storage::new();
+        let mut scheduler: Scheduler = Scheduler::new();
+        scheduler.add_system(tick);
+        scheduler.add_system(tick_twice);
+
+        for _ in 0..3 {
+            scheduler.run(&mut storage);
+        }
+
+        assert!(!scheduler.is_stopped());
+        assert_eq!(LAST.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn registrations_of_one_function_keep_separate_state() {
+        let mut storage: Storage = log_storage();
+        let mut scheduler: Scheduler = Scheduler::new();
+        let first: SystemId = scheduler.add_system(log_calls);
+        scheduler.add_system(log_calls);
+
+        scheduler.run(&mut storage);
+        scheduler.run(&mut storage);
+
+        assert_eq!(logged(&storage), [1, 1, 2, 2]);
+
+        // The other registration doesn't see the skipped calls
+        scheduler.disable(first);
+        scheduler.run(&mut storage);
+        scheduler.enable(first);
+        scheduler.run(&mut storage);
+
+        assert_eq!(logged(&storage), [1, 1, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn local_may_come_before_the_storage() {
+        let mut storage: Storage = log_storage();
+        let mut scheduler: Scheduler = Scheduler::new();
+        scheduler.add_system(log_calls_local_first);
+
+        scheduler.run(&mut storage);
+        scheduler.run(&mut storage);
+
+        assert!(!scheduler.is_stopped());
+        assert_eq!(logged(&storage), [10, 20]);
+    }
+
+    #[test]
+    fn storage_is_borrowed_once() {
+        let mut storage: Storage = Storage::new();
+        storage.add(Counter { calls: 0 });
+
+        let mut scheduler: Scheduler = Scheduler::new();
+        scheduler.add_system(storage_twice);
+        scheduler.run(&mut storage);
+
+        assert!(scheduler.is_stopped());
+    }
+
+    #[test]
+    fn single_writes_through_to_the_component() {
+        let mut storage: Storage = Storage::new();
+        storage.add(Counter { calls: 0 });
+
+        // The wrapper is the component itself, not a copy
+        Single::<Counter>::fetch(&mut Some(&mut storage), &mut ())
+            .unwrap()
+            .calls = 5;
+
+        let mut scheduler: Scheduler = Scheduler::new();
+        scheduler
+            .set_error_handler(|_| ErrorAction::Skip)
+            .add_system(count);
+        scheduler.run(&mut storage);
+        scheduler.run(&mut storage);
+
+        assert_eq!(
+            storage.get_first::<Counter>().map(|counter| counter.calls),
+            Some(7)
+        );
+
+        // Skipped while there are two
+        storage.add(Counter { calls: 0 });
+        scheduler.run(&mut storage);
+
+        assert_eq!(
+            storage.get_first::<Counter>().map(|counter| counter.calls),
+            Some(7)
+        );
     }
 }
@@ -1,48 +1,435 @@
 use std::{
     any::{type_name, Any},
-    collections::{hash_map::DefaultHasher, HashMap},
-    fmt::Debug,
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    fmt::{self, Debug},
     hash::{Hash, Hasher},
 };
 
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::Assets, bundle::Bundle, registry::Registry, scheduler::Commands, tasks::TaskPool,
+};
+
 pub type Token = u64;
+pub type Entity = u64;
 pub type Item = Box<dyn Component>;
 pub type Items = Vec<Item>;
+pub type Owners = Vec<Entity>;
+pub type SparseIndex = HashMap<Entity, usize>;
+
+// How the components of one type are laid out, queries work the same for both
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageType {
+    // Keeps the insertion order, best for components iterated every frame
+    #[default]
+    Table,
+    // Indexed by entity, cheap to insert and remove but the order isn't kept
+    SparseSet,
+}
 
 // TODO: Add macros for 'as_any' and 'as_any_mut' methods
 pub trait Component: Debug + Send + 'static {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn type_name(&self) -> &'static str {
+        type_name::<Self>()
+    }
+}
+
+// What happens when a second instance of a unique component is inserted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UniquePolicy {
+    // Panics, or returns the error from the `try_*` methods
+    #[default]
+    Error,
+    // Takes the component from its previous entity
+    Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueError {
+    pub component: &'static str,
+    pub owner: Entity,
+}
+
+impl fmt::Display for UniqueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "component `{}` is unique and already belongs to entity {}",
+            self.component, self.owner
+        )
+    }
 }
 
+impl std::error::Error for UniqueError {}
+
 pub struct Storage {
     storage: HashMap<Token, Items>,
+    // Entity of every component, index for index with `storage`
+    owners: HashMap<Token, Owners>,
+    // Index of every entity in the columns stored as sparse sets
+    sparse: HashMap<Token, SparseIndex>,
+    uniques: HashMap<Token, UniquePolicy>,
+    // Type name of every column, kept after the column is emptied
+    names: HashMap<Token, &'static str>,
+    entities: BTreeSet<Entity>,
+    pub(crate) next_entity: Entity,
+    pub(crate) registry: Registry,
+    pub(crate) commands: Commands,
+    pub(crate) tasks: TaskPool,
+    pub(crate) assets: Assets,
 }
 
 impl Storage {
     pub fn new() -> Self {
         Self {
             storage: HashMap::new(),
+            owners: HashMap::new(),
+            sparse: HashMap::new(),
+            uniques: HashMap::new(),
+            names: HashMap::new(),
+            entities: BTreeSet::new(),
+            next_entity: 0,
+            registry: Registry::new(),
+            commands: Commands::default(),
+            tasks: TaskPool::default(),
+            assets: Assets::new(),
         }
     }
 
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn commands(&mut self) -> &mut Commands {
+        &mut self.commands
+    }
+
+    pub fn tasks(&mut self) -> &mut TaskPool {
+        &mut self.tasks
+    }
+
+    /// Adds the component as a new entity
     pub fn add<T: Component>(&mut self, component: T) -> &mut Self {
+        let entity: Entity = self.spawn_empty();
+
+        self.insert(entity, component)
+    }
+
+    /// Like `add`, but returns the error of unique components instead of panicking
+    pub fn try_add<T: Component>(&mut self, component: T) -> Result<Entity, UniqueError> {
+        let token: Token = Self::type_to_token::<T>();
+
+        // Checked before spawning to not leave an empty entity behind
+        if self.uniques.get(&token) == Some(&UniquePolicy::Error) {
+            if let Some(owner) = self.entity::<T>(0) {
+                return Err(UniqueError {
+                    component: type_name::<T>(),
+                    owner,
+                });
+            }
+        }
+
+        let entity: Entity = self.spawn_empty();
+
+        self.insert(entity, component);
+        Ok(entity)
+    }
+
+    pub fn spawn<B: Bundle>(&mut self, bundle: B) -> Entity {
+        let entity: Entity = self.spawn_empty();
+
+        bundle.insert(self, entity);
+        entity
+    }
+
+    pub fn spawn_prefab(&mut self, name: &str) -> Option<Entity> {
+        let prefab = self.registry.prefab(name)?;
+        let entity: Entity = self.spawn_empty();
+
+        prefab.insert(self, entity);
+        Some(entity)
+    }
+
+    pub fn spawn_empty(&mut self) -> Entity {
+        let entity: Entity = self.next_entity;
+
+        self.next_entity += 1;
+        self.entities.insert(entity);
+
+        entity
+    }
+
+    /// Attaches the component to the entity, replacing the previous one of the same type
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> &mut Self {
         // Create unique token from component type
         let token: Token = Self::type_to_token::<T>();
 
-        let component: Box<T> = Box::new(component);
+        self.insert_item(token, entity, Box::new(component))
+    }
+
+    /// Fails instead of panicking when the unique component belongs to another entity
+    pub fn try_insert<T: Component>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> Result<&mut Self, UniqueError> {
+        let token: Token = Self::type_to_token::<T>();
+
+        self.try_insert_item(token, entity, Box::new(component))
+    }
+
+    pub(crate) fn insert_item(&mut self, token: Token, entity: Entity, item: Item) -> &mut Self {
+        match self.try_insert_item(token, entity, item) {
+            Ok(storage) => storage,
+            Err(err) => panic!("{err}"),
+        }
+    }
 
-        // Check if it exists
-        self.storage.entry(token).or_insert(vec![]);
+    pub(crate) fn try_insert_item(
+        &mut self,
+        token: Token,
+        entity: Entity,
+        item: Item,
+    ) -> Result<&mut Self, UniqueError> {
+        if let Some(policy) = self.uniques.get(&token).copied() {
+            let owner: Option<Entity> = self
+                .owners
+                .get(&token)
+                .and_then(|owners| owners.iter().find(|owner| **owner != entity).copied());
+
+            if let Some(owner) = owner {
+                match policy {
+                    UniquePolicy::Error => {
+                        return Err(UniqueError {
+                            component: item.type_name(),
+                            owner,
+                        })
+                    }
+                    UniquePolicy::Replace => {
+                        if let Some(index) = self.index_of(token, owner) {
+                            self.remove_at(token, index);
+                        }
+
+                        // Don't leave an empty entity behind
+                        if !self.owners.values().any(|owners| owners.contains(&owner)) {
+                            self.entities.remove(&owner);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.entities.insert(entity);
+        self.next_entity = self.next_entity.max(entity + 1);
+        self.names.entry(token).or_insert_with(|| item.type_name());
+
+        let components: &mut Items = self.storage.entry(token).or_default();
+        let owners: &mut Owners = self.owners.entry(token).or_default();
+
+        // One component of each type per entity
+        match Self::find(self.sparse.get(&token), owners, entity) {
+            Some(index) => components[index] = item,
+            None => {
+                components.push(item);
+                owners.push(entity);
+
+                if let Some(sparse) = self.sparse.get_mut(&token) {
+                    sparse.insert(entity, owners.len() - 1);
+                }
+            }
+        }
+
+        Ok(self)
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> &mut Self {
+        let tokens: Vec<Token> = self.owners.keys().copied().collect();
+
+        for token in tokens {
+            if let Some(index) = self.index_of(token, entity) {
+                self.remove_at(token, index);
+            }
+        }
+
+        self.entities.remove(&entity);
+        self
+    }
+
+    pub fn storage_type<T: Component>(&self) -> StorageType {
+        self.storage_type_of(Self::type_to_token::<T>())
+    }
+
+    /// Changes the layout of the component type, existing components are kept
+    pub fn set_storage_type<T: Component>(&mut self, storage_type: StorageType) -> &mut Self {
+        self.set_storage_type_of(Self::type_to_token::<T>(), storage_type)
+    }
+
+    pub(crate) fn storage_type_of(&self, token: Token) -> StorageType {
+        match self.sparse.contains_key(&token) {
+            true => StorageType::SparseSet,
+            false => StorageType::Table,
+        }
+    }
+
+    pub(crate) fn set_storage_type_of(
+        &mut self,
+        token: Token,
+        storage_type: StorageType,
+    ) -> &mut Self {
+        match storage_type {
+            StorageType::Table => {
+                self.sparse.remove(&token);
+            }
+            StorageType::SparseSet => {
+                let owners: Option<&Owners> = self.owners.get(&token);
+
+                self.sparse.entry(token).or_insert_with(|| {
+                    owners
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                        .map(|(index, owner)| (*owner, index))
+                        .collect()
+                });
+            }
+        }
+
+        self
+    }
+
+    pub fn is_unique<T: Component>(&self) -> bool {
+        self.uniques.contains_key(&Self::type_to_token::<T>())
+    }
+
+    /// Allows a single instance of the component in the storage
+    pub fn set_unique<T: Component>(&mut self, policy: UniquePolicy) -> &mut Self {
+        self.uniques.insert(Self::type_to_token::<T>(), policy);
+        self
+    }
+
+    /// Merges the registry and applies its storage types and unique components
+    pub(crate) fn merge_registry(&mut self, registry: Registry) {
+        for (token, storage_type) in registry.storage_types() {
+            self.set_storage_type_of(token, storage_type);
+        }
+
+        self.uniques.extend(registry.uniques());
+
+        self.registry.merge(registry);
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn entity<T: Component>(&self, index: usize) -> Option<Entity> {
+        let token: Token = Self::type_to_token::<T>();
+
+        self.owners.get(&token)?.get(index).copied()
+    }
+
+    pub fn get_component<T: Component>(&self, entity: Entity) -> Option<&T> {
+        let token: Token = Self::type_to_token::<T>();
+        let index: usize = self.index_of(token, entity)?;
 
-        // Push component
         self.storage
-            .entry(token)
-            .and_modify(|components| components.push(component));
+            .get(&token)
+            .map(|components| component_as_type::<T>(&components[index]))
+    }
+
+    pub fn get_component_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        let token: Token = Self::type_to_token::<T>();
+        let index: usize = self.index_of(token, entity)?;
+
+        self.storage
+            .get_mut(&token)
+            .map(|components| component_as_mut_type::<T>(&mut components[index]))
+    }
+
+    /// Detaches the component, the entity stays alive
+    pub fn remove_component<T: Component>(&mut self, entity: Entity) -> &mut Self {
+        let token: Token = Self::type_to_token::<T>();
+
+        if let Some(index) = self.index_of(token, entity) {
+            self.remove_at(token, index);
+        }
 
         self
     }
 
+    pub(crate) fn column(&self, token: Token) -> Option<(&Items, &Owners)> {
+        Some((self.storage.get(&token)?, self.owners.get(&token)?))
+    }
+
+    pub(crate) fn columns(&self) -> impl Iterator<Item = (Token, &'static str, &Items, &Owners)> {
+        self.storage.iter().filter_map(|(token, components)| {
+            Some((
+                *token,
+                *self.names.get(token)?,
+                components,
+                self.owners.get(token)?,
+            ))
+        })
+    }
+
+    pub(crate) fn sparse_index(&self, token: Token) -> Option<&SparseIndex> {
+        self.sparse.get(&token)
+    }
+
+    fn index_of(&self, token: Token, entity: Entity) -> Option<usize> {
+        Self::find(self.sparse.get(&token), self.owners.get(&token)?, entity)
+    }
+
+    fn find(sparse: Option<&SparseIndex>, owners: &Owners, entity: Entity) -> Option<usize> {
+        match sparse {
+            Some(sparse) => sparse.get(&entity).copied(),
+            None => owners.iter().position(|owner| *owner == entity),
+        }
+    }
+
+    // Tables shift the following components, sparse sets move the last one into the hole
+    fn remove_at(&mut self, token: Token, index: usize) -> Option<Entity> {
+        let components: &mut Items = self.storage.get_mut(&token)?;
+        let owners: &mut Owners = self.owners.get_mut(&token)?;
+
+        if index >= owners.len() {
+            return None;
+        }
+
+        match self.sparse.get_mut(&token) {
+            Some(sparse) => {
+                components.swap_remove(index);
+                let entity: Entity = owners.swap_remove(index);
+
+                sparse.remove(&entity);
+
+                if let Some(moved) = owners.get(index) {
+                    sparse.insert(*moved, index);
+                }
+
+                Some(entity)
+            }
+            None => {
+                components.remove(index);
+                Some(owners.remove(index))
+            }
+        }
+    }
+
+    pub fn count<T: Component>(&self) -> usize {
+        let token: Token = Self::type_to_token::<T>();
+
+        self.owners.get(&token).map_or(0, Vec::len)
+    }
+
     pub fn get_all<T: Component>(&self) -> Option<Vec<&T>> {
         let token: Token = Self::type_to_token::<T>();
 
@@ -93,15 +480,20 @@ impl Storage {
     }
 
     pub fn get<T: Component>(&self, index: usize) -> Option<&T> {
+        // Removing every component of a type leaves an empty list behind
         if let Some(mut components) = self.get_all::<T>() {
-            return Some(components.remove(index));
+            if index < components.len() {
+                return Some(components.remove(index));
+            }
         }
         None
     }
 
     pub fn get_mut<T: Component>(&mut self, index: usize) -> Option<&mut T> {
         if let Some(mut components) = self.get_all_mut::<T>() {
-            return Some(components.remove(index));
+            if index < components.len() {
+                return Some(components.remove(index));
+            }
         }
         None
     }
@@ -119,9 +511,14 @@ impl Storage {
         let token: Token = Self::type_to_token::<T>();
 
         // Remove component
-        self.storage.entry(token).and_modify(|components| {
-            components.remove(index);
-        });
+        let entity: Option<Entity> = self.remove_at(token, index);
+
+        // Entity without components is no longer alive
+        if let Some(entity) = entity {
+            if !self.owners.values().any(|owners| owners.contains(&entity)) {
+                self.entities.remove(&entity);
+            }
+        }
 
         self
     }
@@ -149,3 +546,210 @@ pub fn component_as_type<T: Component>(component: &Box<dyn Component>) -> &T {
 pub fn component_as_mut_type<T: Component>(component: &mut Box<dyn Component>) -> &mut T {
     component.as_any_mut().downcast_mut::<T>().unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Component, Entity, Identification, Storage, StorageType, Token, UniqueError, UniquePolicy,
+    };
+    use std::any::Any;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Tag(u32);
+
+    impl Component for Tag {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Other;
+
+    impl Component for Other {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn sparse_set(count: u32) -> (Storage, Vec<Entity>) {
+        let mut storage: Storage = Storage::new();
+        storage.set_storage_type::<Tag>(StorageType::SparseSet);
+
+        let entities: Vec<Entity> = (0..count).map(|tag| storage.spawn(Tag(tag))).collect();
+
+        (storage, entities)
+    }
+
+    // The index, the owners and the components must agree on every entity
+    fn assert_in_sync(storage: &Storage) {
+        let token: Token = Storage::type_to_token::<Tag>();
+        let (components, owners) = storage.column(token).unwrap();
+        let sparse = storage.sparse_index(token).unwrap();
+
+        assert_eq!(components.len(), owners.len());
+        assert_eq!(sparse.len(), owners.len());
+
+        for (index, owner) in owners.iter().enumerate() {
+            assert_eq!(sparse.get(owner), Some(&index));
+        }
+    }
+
+    #[test]
+    fn removing_from_the_middle_moves_the_last_component() {
+        let (mut storage, entities) = sparse_set(4);
+
+        storage.remove::<Tag>(1);
+
+        assert_in_sync(&storage);
+        assert_eq!(storage.entity::<Tag>(1), Some(entities[3]));
+        assert_eq!(storage.get_component::<Tag>(entities[1]), None);
+        assert!(!storage.contains(entities[1]));
+
+        for tag in [0, 2, 3] {
+            assert_eq!(
+                storage.get_component::<Tag>(entities[tag as usize]),
+                Some(&Tag(tag))
+            );
+        }
+    }
+
+    #[test]
+    fn removing_the_last_component_moves_nothing() {
+        let (mut storage, entities) = sparse_set(3);
+
+        storage.remove::<Tag>(2);
+
+        assert_in_sync(&storage);
+        assert_eq!(storage.count::<Tag>(), 2);
+        assert_eq!(storage.get_component::<Tag>(entities[2]), None);
+        assert_eq!(storage.get_component::<Tag>(entities[0]), Some(&Tag(0)));
+        assert_eq!(storage.get_component::<Tag>(entities[1]), Some(&Tag(1)));
+
+        // Out of range is ignored
+        storage.remove::<Tag>(5);
+
+        assert_in_sync(&storage);
+        assert_eq!(storage.count::<Tag>(), 2);
+    }
+
+    #[test]
+    fn emptied_type_has_no_first_component() {
+        let mut storage: Storage = Storage::new();
+        storage.add(Other);
+
+        storage.remove::<Other>(0);
+
+        assert_eq!(storage.get_first::<Other>(), None);
+        assert_eq!(storage.get_first_mut::<Other>(), None);
+        assert_eq!(storage.get::<Other>(3), None);
+    }
+
+    #[test]
+    fn lookups_stay_valid_after_many_removals() {
+        let (mut storage, entities) = sparse_set(8);
+
+        storage
+            .despawn(entities[0])
+            .remove_component::<Tag>(entities[5])
+            .despawn(entities[7]);
+        assert_in_sync(&storage);
+
+        storage.insert(entities[5], Tag(50));
+        storage.get_component_mut::<Tag>(entities[3]).unwrap().0 = 30;
+        assert_in_sync(&storage);
+
+        let expected: [Option<Tag>; 8] = [
+            None,
+            Some(Tag(1)),
+            Some(Tag(2)),
+            Some(Tag(30)),
+            Some(Tag(4)),
+            Some(Tag(50)),
+            Some(Tag(6)),
+            None,
+        ];
+
+        for (entity, tag) in entities.iter().zip(expected) {
+            assert_eq!(storage.get_component::<Tag>(*entity).copied(), tag);
+        }
+
+        // Removed components keep the entity alive, despawned ones don't
+        assert!(storage.contains(entities[5]));
+        assert!(!storage.contains(entities[7]));
+    }
+
+    #[test]
+    fn switching_layouts_keeps_the_components() {
+        let (mut storage, entities) = sparse_set(4);
+
+        storage.remove::<Tag>(0);
+        storage.set_storage_type::<Tag>(StorageType::Table);
+        storage.remove::<Tag>(0);
+        storage.set_storage_type::<Tag>(StorageType::SparseSet);
+
+        assert_in_sync(&storage);
+        assert_eq!(storage.count::<Tag>(), 2);
+        assert_eq!(storage.get_component::<Tag>(entities[1]), Some(&Tag(1)));
+        assert_eq!(storage.get_component::<Tag>(entities[2]), Some(&Tag(2)));
+    }
+
+    #[test]
+    fn unique_error_keeps_the_first_instance() {
+        let mut storage: Storage = Storage::new();
+        storage.set_unique::<Tag>(UniquePolicy::Error);
+
+        let owner: Entity = storage.try_add(Tag(0)).unwrap();
+        let other: Entity = storage.spawn_empty();
+
+        assert_eq!(
+            storage.try_add(Tag(1)),
+            Err(UniqueError {
+                component: std::any::type_name::<Tag>(),
+                owner
+            })
+        );
+        assert!(storage.try_insert(other, Tag(2)).is_err());
+        // Replacing the component of its own entity is fine
+        assert!(storage.try_insert(owner, Tag(3)).is_ok());
+
+        assert_eq!(storage.count::<Tag>(), 1);
+        assert_eq!(storage.get_component::<Tag>(owner), Some(&Tag(3)));
+        // No empty entity is left behind by the failed add
+        assert_eq!(storage.entities().count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "is unique")]
+    fn unique_error_panics_on_insert() {
+        let mut storage: Storage = Storage::new();
+        storage.set_unique::<Tag>(UniquePolicy::Error);
+
+        storage.add(Tag(0)).add(Tag(1));
+    }
+
+    #[test]
+    fn unique_replace_moves_the_component() {
+        let mut storage: Storage = Storage::new();
+        storage.set_unique::<Tag>(UniquePolicy::Replace);
+
+        let first: Entity = storage.spawn(Tag(0));
+        let second: Entity = storage.spawn((Tag(1), Other));
+        let third: Entity = storage.spawn(Tag(2));
+
+        assert_eq!(storage.count::<Tag>(), 1);
+        assert_eq!(storage.get_component::<Tag>(third), Some(&Tag(2)));
+        // Entities left without components are despawned, the others stay
+        assert!(!storage.contains(first));
+        assert!(storage.contains(second));
+        assert_eq!(storage.get_component::<Other>(second), Some(&Other));
+    }
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use crate::assets::update_assets;
+use crate::scheduler::{
+    system::{SystemId, SystemOutput},
+    ErrorHandler, IntoSystem, Scheduler, SystemParam,
+};
+use crate::tasks::poll_tasks;
+use crate::time::{update_time, Time, TimePlugin};
+use crate::{Plugin, Storage};
+
+pub type Extract = Box<dyn FnMut(&mut Storage, &mut Storage) + Send>;
+
+// Isolated world with its own schedule, owned by the `App`
+pub struct SubApp {
+    pub(crate) storage: Storage,
+    pub(crate) scheduler: Scheduler,
+    pub(crate) update_mode: UpdateMode,
+    extract: Option<Extract>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateMode {
+    // Stepped every frame right after the main world
+    WithApp,
+    // Stepped only by explicit `update` calls
+    Manual,
+}
+
+impl SubApp {
+    pub fn new(update_mode: UpdateMode) -> Self {
+        let mut scheduler: Scheduler = Scheduler::new();
+        let mut storage: Storage = Storage::new();
+
+        scheduler.add_plugin::<TimePlugin>();
+        storage.add(Time::default());
+
+        Self {
+            storage,
+            scheduler,
+            update_mode,
+            extract: None,
+        }
+    }
+
+    pub fn add_system<F: IntoSystem<Params> + 'static, Params: SystemParam>(
+        &mut self,
+        system: F,
+    ) -> SystemId {
+        self.scheduler.add_system(system)
+    }
+
+    pub fn add_startup_system<F: IntoSystem<Params> + 'static, Params: SystemParam>(
+        &mut self,
+        system: F,
+    ) -> SystemId {
+        self.scheduler.add_startup_system(system)
+    }
+
+    pub fn add_interval_system<F, Params>(&mut self, system: F, call_interval: Duration) -> SystemId
+    where
+        F: IntoSystem<Params> + 'static,
+        Params: SystemParam,
+    {
+        self.scheduler.add_interval_system(system, call_interval)
+    }
+
+    pub fn add_exclusive_system<F, Out>(&mut self, system: F) -> SystemId
+    where
+        F: FnMut(&mut Storage) -> Out + Send + 'static,
+        Out: SystemOutput,
+    {
+        self.scheduler.add_exclusive_system(system)
+    }
+
+    pub fn add_plugin<P: Plugin>(&mut self) -> &mut Self {
+        self.scheduler.add_plugin::<P>();
+        self
+    }
+
+    pub fn set_error_handler(&mut self, error_handler: ErrorHandler) -> &mut Self {
+        self.scheduler.set_error_handler(error_handler);
+        self
+    }
+
+    /// Called with the main world and this world before every update of this world
+    pub fn set_extract<F>(&mut self, extract: F) -> &mut Self
+    where
+        F: FnMut(&mut Storage, &mut Storage) + Send + 'static,
+    {
+        self.extract = Some(Box::new(extract));
+        self
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    // Stopped sub-apps are still extracted into but run no systems
+    pub fn is_stopped(&self) -> bool {
+        self.scheduler.is_stopped()
+    }
+
+    pub fn storage_mut(&mut self) -> &mut Storage {
+        &mut self.storage
+    }
+
+    pub fn extract(&mut self, main: &mut Storage) -> &mut Self {
+        if let Some(extract) = self.extract.as_mut() {
+            extract(main, &mut self.storage);
+        }
+
+        self
+    }
+
+    pub fn update(&mut self) -> &mut Self {
+        update_time(&mut self.storage);
+        poll_tasks(&mut self.storage);
+        update_assets(&mut self.storage);
+        self.scheduler.run(&mut self.storage);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubApp, UpdateMode};
+    use crate::{storage::Component, App, Config, ScriptedInput, Storage};
+    use macroquad::prelude::BLACK;
+    use std::any::Any;
+
+    // Updates of a world
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    struct Ticks(u32);
+
+    // Main world ticks seen by the sub-app, in order
+    #[derive(Debug, Default)]
+    struct Seen(Vec<u32>);
+
+    impl Component for Ticks {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    impl Component for Seen {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    fn tick(storage: &mut Storage) {
+        match storage.get_first_mut::<Ticks>() {
+            Some(ticks) => ticks.0 += 1,
+            None => {
+                storage.add(Ticks(1));
+            }
+        }
+    }
+
+    fn ticks(storage: &Storage) -> u32 {
+        storage.get_first::<Ticks>().map_or(0, |ticks| ticks.0)
+    }
+
+    fn sub_app(update_mode: UpdateMode) -> SubApp {
+        let mut sub_app: SubApp = SubApp::new(update_mode);
+        sub_app.add_system(tick);
+        sub_app
+    }
+
+    fn app() -> App {
+        let mut app: App = App::new(Config {
+            background_color: BLACK,
+        });
+        // No window to read from
+        app.set_input_backend(ScriptedInput::new());
+        app.add_system(tick);
+        app
+    }
+
+    #[test]
+    fn manual_sub_apps_wait_for_explicit_updates() {
+        let mut app: App = app();
+        app.add_sub_app("with", sub_app(UpdateMode::WithApp))
+            .add_sub_app("manual", sub_app(UpdateMode::Manual));
+
+        app.update().update().update();
+
+        assert_eq!(ticks(app.sub_app("with").unwrap().storage()), 3);
+        assert_eq!(ticks(app.sub_app("manual").unwrap().storage()), 0);
+
+        app.update_sub_app("manual");
+
+        assert_eq!(ticks(app.storage()), 3);
+        assert_eq!(ticks(app.sub_app("with").unwrap().storage()), 3);
+        assert_eq!(ticks(app.sub_app("manual").unwrap().storage()), 1);
+    }
+
+    #[test]
+    fn extract_runs_before_every_update() {
+        let mut sub_app: SubApp = SubApp::new(UpdateMode::WithApp);
+        sub_app
+            .set_extract(|main, world| {
+                let ticks: Ticks = main.get_first::<Ticks>().copied().unwrap_or_default();
+
+                match world.get_first_mut::<Ticks>() {
+                    Some(copy) => *copy = ticks,
+                    None => {
+                        world.add(ticks);
+                    }
+                }
+            })
+            .add_system(|storage: &mut Storage| {
+                let ticks: u32 = ticks(storage);
+
+                match storage.get_first_mut::<Seen>() {
+                    Some(seen) => seen.0.push(ticks),
+                    None => {
+                        storage.add(Seen(vec![ticks]));
+                    }
+                }
+            });
+
+        let mut app: App = app();
+        app.add_sub_app("copy", sub_app);
+
+        app.update().update();
+        app.update_sub_app("copy");
+
+        let seen: &Seen = app
+            .sub_app("copy")
+            .and_then(|sub_app| sub_app.storage().get_first::<Seen>())
+            .unwrap();
+
+        assert_eq!(seen.0, [1, 2, 2]);
+    }
+
+    #[test]
+    fn worlds_are_isolated() {
+        let mut app: App = app();
+        app.add_sub_app("other", sub_app(UpdateMode::WithApp));
+        app.storage_mut().add(Seen::default());
+
+        app.update();
+
+        let other: &mut SubApp = app.sub_app_mut("other").unwrap();
+        other.storage_mut().add(Ticks(100));
+
+        assert_eq!(other.storage().count::<Seen>(), 0);
+        assert_eq!(ticks(app.storage()), 1);
+        assert_eq!(app.storage().count::<Ticks>(), 1);
+    }
+
+    #[test]
+    fn failed_sub_app_stops_alone() {
+        let mut failing: SubApp = SubApp::new(UpdateMode::WithApp);
+        failing.add_system(|| Err::<(), _>("out of moves"));
+
+        let mut app: App = app();
+        app.add_sub_app("failing", failing)
+            .add_sub_app("other", sub_app(UpdateMode::WithApp));
+
+        app.update().update();
+
+        assert!(!app.is_stopped());
+        assert!(app.sub_app("failing").unwrap().is_stopped());
+        assert!(!app.sub_app("other").unwrap().is_stopped());
+        assert_eq!(ticks(app.storage()), 2);
+        assert_eq!(ticks(app.sub_app("other").unwrap().storage()), 2);
+    }
+}
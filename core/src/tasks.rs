@@ -0,0 +1,200 @@
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{storage::Component, Storage};
+
+pub type Delivery = Box<dyn FnOnce(&mut Storage) + Send>;
+pub type Task = Pin<Box<dyn Future<Output = Delivery> + Send>>;
+
+// Futures polled once per frame, their results are delivered back into the storage
+#[derive(Default)]
+pub struct TaskPool {
+    tasks: Vec<Task>,
+}
+
+impl TaskPool {
+    /// The output is added to the storage as a new entity
+    pub fn spawn<T, F>(&mut self, future: F) -> &mut Self
+    where
+        T: Component,
+        F: Future<Output = T> + Send + 'static,
+    {
+        self.spawn_with(future, |output, storage| {
+            storage.add(output);
+        })
+    }
+
+    /// The output is sent as an event
+    pub fn spawn_event<T, F>(&mut self, future: F) -> &mut Self
+    where
+        T: Debug + Send + 'static,
+        F: Future<Output = T> + Send + 'static,
+    {
+        self.spawn_with(future, |output, storage| {
+            storage.send_event(output);
+        })
+    }
+
+    pub fn spawn_with<T, F, D>(&mut self, future: F, deliver: D) -> &mut Self
+    where
+        T: Send + 'static,
+        F: Future<Output = T> + Send + 'static,
+        D: FnOnce(T, &mut Storage) + Send + 'static,
+    {
+        self.tasks.push(Box::pin(async move {
+            let output: T = future.await;
+
+            Box::new(move |storage: &mut Storage| deliver(output, storage)) as Delivery
+        }));
+
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // Poll every task once, keeping the unfinished ones
+    fn poll(&mut self) -> Vec<Delivery> {
+        let waker: Waker = noop_waker();
+        let mut context: Context = Context::from_waker(&waker);
+        let mut deliveries: Vec<Delivery> = Vec::new();
+
+        self.tasks
+            .retain_mut(|task| match task.as_mut().poll(&mut context) {
+                Poll::Ready(delivery) => {
+                    deliveries.push(delivery);
+                    false
+                }
+                Poll::Pending => true,
+            });
+
+        deliveries
+    }
+}
+
+impl Debug for TaskPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskPool")
+            .field("tasks", &self.tasks.len())
+            .finish()
+    }
+}
+
+pub(crate) fn poll_tasks(storage: &mut Storage) {
+    for delivery in storage.tasks.poll() {
+        delivery(storage);
+    }
+}
+
+// Tasks are polled every frame anyway, nobody has to be woken up
+pub(crate) fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(ptr::null(), &VTABLE),
+        |_| (),
+        |_| (),
+        |_| (),
+    );
+
+    unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{storage::Component, App, Config, ScriptedInput};
+    use macroquad::prelude::BLACK;
+    use std::{
+        any::Any,
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Ticket(u32);
+
+    impl Component for Ticket {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+    }
+
+    // Ready after being polled a number of times
+    struct Countdown {
+        polls: u32,
+        ticket: Ticket,
+    }
+
+    impl Future for Countdown {
+        type Output = Ticket;
+
+        fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Ticket> {
+            if self.polls == 0 {
+                return Poll::Ready(self.ticket);
+            }
+
+            self.polls -= 1;
+            Poll::Pending
+        }
+    }
+
+    fn app() -> App {
+        let mut app: App = App::new(Config {
+            background_color: BLACK,
+        });
+        app.set_input_backend(ScriptedInput::new());
+        app
+    }
+
+    #[test]
+    fn pending_task_is_delivered_on_a_later_update() {
+        let mut app: App = app();
+        app.storage_mut().tasks().spawn(Countdown {
+            polls: 2,
+            ticket: Ticket(7),
+        });
+
+        app.update().update();
+
+        assert_eq!(app.storage().count::<Ticket>(), 0);
+        assert_eq!(app.storage_mut().tasks().len(), 1);
+
+        app.update();
+
+        assert_eq!(app.storage().get_first::<Ticket>(), Some(&Ticket(7)));
+        assert!(app.storage_mut().tasks().is_empty());
+    }
+
+    #[test]
+    fn finished_tasks_are_delivered_in_the_same_update() {
+        let mut app: App = app();
+        app.storage_mut()
+            .tasks()
+            .spawn_event(async { Ticket(3) })
+            .spawn_with(async { 4 }, |value, storage| {
+                storage.add(Ticket(value));
+            });
+
+        // Not polled until the app updates
+        assert_eq!(app.storage_mut().read_events::<Ticket>(), []);
+
+        app.update();
+
+        assert_eq!(app.storage_mut().read_events::<Ticket>(), [Ticket(3)]);
+        assert_eq!(app.storage().get_first::<Ticket>(), Some(&Ticket(4)));
+        assert!(app.storage_mut().tasks().is_empty());
+    }
+}
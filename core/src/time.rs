@@ -0,0 +1,420 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{storage::Component, Plugin, PluginBuilder, Storage};
+
+// App time, advanced once per update of the world
+#[derive(Debug, Clone, Default)]
+pub struct Time {
+    delta: Duration,
+    elapsed: Duration,
+    last_update: Option<Instant>,
+    // Fixed delta of every update instead of the measured one
+    step: Option<Duration>,
+    // Recorded deltas, used up one per update before the step or the clock
+    playback: VecDeque<Duration>,
+}
+
+impl Time {
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn step(&self) -> Option<Duration> {
+        self.step
+    }
+
+    /// Makes every update advance by the same delta, for tests and replays
+    pub fn set_step(&mut self, step: Option<Duration>) {
+        self.step = step;
+    }
+
+    /// Replays the deltas of a recorded session, one per update
+    pub fn play_back<I: IntoIterator<Item = Duration>>(&mut self, deltas: I) {
+        self.playback = deltas.into_iter().collect();
+    }
+
+    // Measure the time passed since the previous update
+    pub fn update(&mut self) {
+        if let Some(delta) = self.playback.pop_front() {
+            self.advance(delta);
+            return;
+        }
+
+        if let Some(step) = self.step {
+            self.advance(step);
+            return;
+        }
+
+        let now: Instant = Instant::now();
+        let delta: Duration = match self.last_update {
+            Some(last_update) => now - last_update,
+            None => Duration::ZERO,
+        };
+
+        self.last_update = Some(now);
+        self.advance(delta);
+    }
+
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+}
+
+pub(crate) fn update_time(storage: &mut Storage) {
+    match storage.get_first_mut::<Time>() {
+        Some(time) => time.update(),
+        None => {
+            storage.add(Time::default());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimerMode {
+    Once,
+    Repeating,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timer {
+    duration: Duration,
+    elapsed: Duration,
+    mode: TimerMode,
+    paused: bool,
+    finished: bool,
+    // How many times the timer finished during the last tick
+    times_finished: u32,
+}
+
+impl Timer {
+    pub fn new(duration: Duration, mode: TimerMode) -> Self {
+        Self {
+            duration,
+            elapsed: Duration::ZERO,
+            mode,
+            paused: false,
+            finished: false,
+            times_finished: 0,
+        }
+    }
+
+    pub fn from_seconds(seconds: f32, mode: TimerMode) -> Self {
+        Self::new(Duration::from_secs_f32(seconds), mode)
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        self.times_finished = 0;
+
+        if self.paused || (self.finished && self.mode == TimerMode::Once) {
+            return self;
+        }
+
+        self.elapsed += delta;
+
+        if self.elapsed < self.duration {
+            return self;
+        }
+
+        match self.mode {
+            TimerMode::Once => {
+                self.elapsed = self.duration;
+                self.times_finished = 1;
+            }
+            TimerMode::Repeating if self.duration.is_zero() => {
+                self.elapsed = Duration::ZERO;
+                self.times_finished = 1;
+            }
+            TimerMode::Repeating => {
+                let duration: u128 = self.duration.as_nanos();
+                let elapsed: u128 = self.elapsed.as_nanos();
+
+                self.times_finished = (elapsed / duration) as u32;
+                self.elapsed = Duration::from_nanos((elapsed % duration) as u64);
+            }
+        }
+
+        self.finished = true;
+        self
+    }
+
+    /// Finished during the last tick
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// Once timers stay finished, repeating ones report the last tick
+    pub fn finished(&self) -> bool {
+        match self.mode {
+            TimerMode::Once => self.finished,
+            TimerMode::Repeating => self.just_finished(),
+        }
+    }
+
+    pub fn times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.elapsed)
+    }
+
+    pub fn percent(&self) -> f32 {
+        match self.duration.is_zero() {
+            true => 1.,
+            false => self.elapsed.as_secs_f32() / self.duration.as_secs_f32(),
+        }
+    }
+
+    pub fn mode(&self) -> TimerMode {
+        self.mode
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        self.times_finished = 0;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+// Advances every timer and stopwatch by the app's delta time
+pub struct TimePlugin;
+
+impl Plugin for TimePlugin {
+    fn new(builder: &mut PluginBuilder) {
+        builder.add_system(tick_timers);
+    }
+}
+
+pub fn tick_timers(storage: &mut Storage) {
+    let delta: Duration = match storage.get_first::<Time>() {
+        Some(time) => time.delta(),
+        None => return,
+    };
+
+    if let Some(mut timers) = storage.get_all_mut::<Timer>() {
+        timers.iter_mut().for_each(|timer| {
+            timer.tick(delta);
+        });
+    }
+
+    if let Some(mut stopwatches) = storage.get_all_mut::<Stopwatch>() {
+        stopwatches.iter_mut().for_each(|stopwatch| {
+            stopwatch.tick(delta);
+        });
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Time {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Timer {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Stopwatch {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Time, Timer, TimerMode};
+    use std::time::Duration;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn once_timer_stays_finished() {
+        let mut timer: Timer = Timer::new(MS * 100, TimerMode::Once);
+
+        assert!(!timer.tick(MS * 60).finished());
+        assert!(timer.tick(MS * 60).just_finished());
+        assert_eq!(timer.elapsed(), MS * 100);
+
+        assert!(timer.tick(MS * 60).finished());
+        assert!(!timer.just_finished());
+    }
+
+    #[test]
+    fn repeating_timer_wraps_around() {
+        let mut timer: Timer = Timer::new(MS * 100, TimerMode::Repeating);
+
+        assert_eq!(timer.tick(MS * 70).times_finished(), 0);
+        assert_eq!(timer.tick(MS * 70).times_finished(), 1);
+        assert_eq!(timer.elapsed(), MS * 40);
+
+        // Exactly on the period leaves nothing over
+        assert_eq!(timer.tick(MS * 60).times_finished(), 1);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        assert!(!timer.tick(MS * 10).finished());
+    }
+
+    #[test]
+    fn repeating_timer_counts_several_periods_in_one_tick() {
+        let mut timer: Timer = Timer::new(MS * 100, TimerMode::Repeating);
+
+        timer.tick(MS * 50);
+
+        assert_eq!(timer.tick(MS * 380).times_finished(), 4);
+        assert_eq!(timer.elapsed(), MS * 30);
+        assert_eq!(timer.remaining(), MS * 70);
+    }
+
+    #[test]
+    fn zero_duration_repeating_timer_finishes_once_per_tick() {
+        let mut timer: Timer = Timer::new(Duration::ZERO, TimerMode::Repeating);
+
+        assert_eq!(timer.tick(MS * 5).times_finished(), 1);
+        assert_eq!(timer.tick(Duration::ZERO).times_finished(), 1);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+    }
+
+    #[test]
+    fn paused_timer_does_not_advance() {
+        let mut timer: Timer = Timer::new(MS * 100, TimerMode::Repeating);
+
+        timer.pause();
+
+        assert_eq!(timer.tick(MS * 250).times_finished(), 0);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        timer.unpause();
+
+        assert_eq!(timer.tick(MS * 250).times_finished(), 2);
+    }
+
+    #[test]
+    fn fixed_step_drives_timers_deterministically() {
+        let mut time: Time = Time::default();
+        let mut timer: Timer = Timer::new(MS * 100, TimerMode::Repeating);
+        let mut finished: u32 = 0;
+
+        time.set_step(Some(MS * 16));
+
+        for _ in 0..60 {
+            time.update();
+            finished += timer.tick(time.delta()).times_finished();
+        }
+
+        assert_eq!(time.elapsed(), MS * 960);
+        assert_eq!(finished, 9);
+        assert_eq!(timer.elapsed(), MS * 60);
+    }
+
+    #[test]
+    fn played_back_deltas_come_before_the_step() {
+        let mut time: Time = Time::default();
+        let mut deltas: Vec<Duration> = Vec::new();
+
+        time.set_step(Some(MS * 16));
+        time.play_back([MS * 7, MS * 40]);
+
+        for _ in 0..3 {
+            time.update();
+            deltas.push(time.delta());
+        }
+
+        assert_eq!(deltas, [MS * 7, MS * 40, MS * 16]);
+        assert_eq!(time.elapsed(), MS * 63);
+    }
+}
@@ -0,0 +1,136 @@
+use core::{
+    bundle::Bundle,
+    scene::{Scene, SceneEntity},
+    storage::Component,
+    Entity, Scheduler, Storage,
+};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Name(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Health(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Armor(u32);
+
+#[derive(Bundle)]
+struct Guard {
+    name: Name,
+    // Nested bundles are flattened into the same entity
+    stats: (Health, Armor),
+}
+
+#[derive(Bundle)]
+struct Wall(Armor, Health);
+
+impl Component for Name {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Health {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Armor {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn guard() -> Guard {
+    Guard {
+        name: Name("guard".to_string()),
+        stats: (Health(10), Armor(2)),
+    }
+}
+
+#[test]
+fn derived_bundles_insert_every_field() {
+    let mut storage: Storage = Storage::new();
+
+    let guard: Entity = storage.spawn(guard());
+    let wall: Entity = storage.spawn(Wall(Armor(9), Health(100)));
+
+    assert_eq!(
+        storage.get_component::<Name>(guard),
+        Some(&Name("guard".to_string()))
+    );
+    assert_eq!(storage.get_component::<Health>(guard), Some(&Health(10)));
+    assert_eq!(storage.get_component::<Armor>(guard), Some(&Armor(2)));
+    assert_eq!(storage.get_component::<Name>(wall), None);
+    assert_eq!(storage.get_component::<Health>(wall), Some(&Health(100)));
+    assert_eq!(storage.get_component::<Armor>(wall), Some(&Armor(9)));
+}
+
+#[test]
+fn tuple_bundles_insert_every_element() {
+    let mut storage: Storage = Storage::new();
+
+    let entity: Entity = storage.spawn((Name("tuple".to_string()), (Health(1),), Armor(3)));
+
+    assert_eq!(storage.get_component::<Health>(entity), Some(&Health(1)));
+    assert_eq!(storage.get_component::<Armor>(entity), Some(&Armor(3)));
+    assert_eq!(storage.count::<Name>(), 1);
+}
+
+#[test]
+fn scene_entities_spawn_prefabs_by_name() {
+    let mut storage: Storage = Storage::new();
+    let mut scheduler: Scheduler = Scheduler::new();
+    scheduler
+        .register_component::<Health>()
+        .register_prefab("guard", guard)
+        .register_prefab("wall", || Wall(Armor(9), Health(100)));
+    scheduler.flush_registry(&mut storage);
+
+    let scene: Scene = Scene {
+        entities: vec![
+            SceneEntity {
+                prefab: Some("guard".to_string()),
+                components: [("Health".to_string(), 25.into())].into(),
+            },
+            SceneEntity {
+                prefab: Some("wall".to_string()),
+                ..SceneEntity::default()
+            },
+        ],
+    };
+    let entities: Vec<Entity> = scene.spawn(&mut storage).unwrap();
+
+    let mut prefabs: Vec<&str> = storage.registry().prefabs().collect();
+    prefabs.sort();
+
+    assert_eq!(prefabs, ["guard", "wall"]);
+    assert_eq!(
+        storage.get_component::<Health>(entities[0]),
+        Some(&Health(25))
+    );
+    assert_eq!(storage.get_component::<Armor>(entities[0]), Some(&Armor(2)));
+    assert_eq!(
+        storage.get_component::<Name>(entities[0]),
+        Some(&Name("guard".to_string()))
+    );
+    assert_eq!(
+        storage.get_component::<Health>(entities[1]),
+        Some(&Health(100))
+    );
+}
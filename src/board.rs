@@ -0,0 +1,79 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+use crate::{cfg::CELL_COUNT, game::Position, player::Direction};
+use core::{storage::Component, Storage};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Topology {
+    // Leaving the board kills the snake
+    #[default]
+    Bounded,
+    // Leaving one edge enters from the opposite one
+    Toroidal,
+}
+
+// Size and shape of the grid, set by the level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Board {
+    pub width: i32,
+    pub height: i32,
+    pub topology: Topology,
+}
+
+impl Board {
+    /// The board of the level, or the default one
+    pub fn of(storage: &Storage) -> Self {
+        storage.get_first::<Board>().copied().unwrap_or_default()
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        (0..self.width).contains(&position.0) && (0..self.height).contains(&position.1)
+    }
+
+    /// Brings the position back onto a toroidal board
+    pub fn wrap(&self, position: Position) -> Position {
+        match self.topology {
+            Topology::Bounded => position,
+            Topology::Toroidal => Position(
+                position.0.rem_euclid(self.width),
+                position.1.rem_euclid(self.height),
+            ),
+        }
+    }
+
+    /// The neighbouring cell in the direction
+    pub fn step(&self, position: Position, direction: Direction) -> Position {
+        let (x, y): (i32, i32) = direction.offset();
+
+        self.wrap(Position(position.0 + x, position.1 + y))
+    }
+
+    pub fn rand_position<R: Rng>(&self, rng: &mut R) -> Position {
+        Position::rand(rng, 0..self.width, 0..self.height)
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            width: CELL_COUNT as i32,
+            height: CELL_COUNT as i32,
+            topology: Topology::default(),
+        }
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Board {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
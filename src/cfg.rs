@@ -1,5 +1,5 @@
 use core::app::Config;
-use macroquad::prelude::{Color, BLACK, GREEN, PURPLE};
+use macroquad::prelude::{Color, BLACK, GRAY, GREEN, PURPLE};
 use std::time::Duration;
 
 // App config
@@ -9,6 +9,11 @@ pub const APP_CONFIG: Config = Config {
 
 // Game config
 pub const GAME_OVER: &str = "Game over.";
+pub const VICTORY: &str = "You win!";
+pub const SETTINGS: &str = "Settings";
+pub const LEVEL_SCENE: &str = "scenes/level1.scn";
+pub const KEYBINDINGS: &str = "config/keybindings.json";
+pub const FOOD_TYPES: &str = "config/food.json";
 
 // Window
 pub const WINDOW_TITLE: &str = "Snake game";
@@ -27,9 +32,20 @@ pub const SNAKE_Y: i32 = (CELL_COUNT - 1) as i32;
 pub const SNAKE_SIZE: f32 = CELL_SIZE;
 pub const SNAKE_COLOR: Color = GREEN;
 pub const SNAKE_STEP_INTERVAL: Duration = Duration::from_millis(125);
+// Turns remembered between two steps
+pub const SNAKE_TURN_BUFFER: usize = 3;
+
+// Gestures, in pixels
+pub const SWIPE_THRESHOLD: f32 = CELL_SIZE;
+pub const TAP_TOLERANCE: f32 = CELL_SIZE / 2.;
+pub const TAP_DEAD_ZONE: f32 = CELL_SIZE / 2.;
 
 // Food
 pub const MAX_FOOD: u8 = 1;
 pub const FOOD_SIZE: f32 = CELL_SIZE;
 pub const FOOD_COLOR: Color = PURPLE;
 pub const FOOD_SPAWN_INTERVAL: Duration = SNAKE_STEP_INTERVAL;
+
+// Obstacles
+pub const OBSTACLE_SIZE: f32 = CELL_SIZE;
+pub const OBSTACLE_COLOR: Color = GRAY;
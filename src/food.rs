@@ -1,60 +1,238 @@
 use crate::{
-    cfg::{CELL_COUNT, FOOD_COLOR, FOOD_SIZE, FOOD_SPAWN_INTERVAL, MAX_FOOD},
-    game::Position,
+    board::Board,
+    cfg::{FOOD_COLOR, FOOD_SIZE, FOOD_SPAWN_INTERVAL, FOOD_TYPES, MAX_FOOD},
+    game::{GameRng, Position},
+    game_over::{GameOver, Simulation},
+    level::Level,
+    occupancy::Occupancy,
     Rect, Shape,
 };
-use core::{storage::Component, Plugin, PluginBuilder, Storage};
-use std::any::Any;
+use core::{
+    storage::Component, system::SystemId, Plugin, PluginBuilder, Storage, StorageType, Time, Timer,
+    TimerMode,
+};
+use macroquad::prelude::Color;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, fmt, fs, io, path::Path};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Food {
     shape: Rect,
     position: Position,
+    kind: FoodKind,
+    // Bonus food disappears when the timer finishes
+    expires: Option<Timer>,
+}
+
+// What eating the food does to the snake
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    Grow(usize),
+    // Cuts segments off the tail, the head is never removed
+    Shrink(usize),
+    // Multiplies the steps per second for a while
+    Speed { factor: f32, seconds: f32 },
+}
+
+// One kind of food, read from the food types file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FoodKind {
+    pub name: String,
+    #[serde(with = "crate::ColorDef")]
+    pub color: Color,
+    // Relative chance to be picked when food spawns
+    pub weight: u32,
+    pub score: u32,
+    pub effect: Effect,
+    // Seconds until the food expires, it stays forever without one
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+}
+
+impl Default for FoodKind {
+    fn default() -> Self {
+        Self {
+            name: "normal".to_string(),
+            color: FOOD_COLOR,
+            weight: 1,
+            score: 1,
+            effect: Effect::Grow(1),
+            lifetime: None,
+        }
+    }
+}
+
+// Every kind of food which may spawn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FoodTypes(pub Vec<FoodKind>);
+
+impl FoodTypes {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FoodTypesError> {
+        let data: String = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FoodKind> {
+        self.0.iter().find(|kind| kind.name == name)
+    }
+
+    /// Random kind by weight, normal food if none can be picked
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> FoodKind {
+        self.0
+            .choose_weighted(rng, |kind| kind.weight)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Default for FoodTypes {
+    fn default() -> Self {
+        Self(vec![FoodKind::default()])
+    }
 }
 
 impl Plugin for Food {
     fn new(builder: &mut PluginBuilder) {
         builder
-            .add_interval_system(Food::spawn, FOOD_SPAWN_INTERVAL)
-            .add_system(Food::draw);
+            .register_component::<Food>()
+            // Eaten and respawned all the time
+            .register_storage::<Food>(StorageType::SparseSet)
+            .register_prefab("food", Food::default);
+        builder.add_startup_system(load_food_types);
+
+        let simulation: Vec<SystemId> = vec![
+            builder.add_interval_system(Food::spawn, FOOD_SPAWN_INTERVAL),
+            builder.add_system(Food::expire),
+        ];
+        Simulation::add(builder, simulation);
+        builder.add_system(Food::draw);
     }
 }
 
+// Normal food in the middle of the board, scenes spawning the prefab stay the same on replay
 impl Default for Food {
     fn default() -> Self {
-        let position = Position::rand(0..CELL_COUNT as i32, 0..CELL_COUNT as i32);
+        let board: Board = Board::default();
 
+        Self::at(Position(board.width / 2, board.height / 2))
+    }
+}
+
+// Methods
+impl Food {
+    /// Normal food
+    pub fn at(position: Position) -> Self {
+        Self::new(position, FoodKind::default())
+    }
+
+    pub fn new(position: Position, kind: FoodKind) -> Self {
         Food {
             shape: Rect {
                 x: Position::compute(position.0),
                 y: Position::compute(position.1),
                 width: FOOD_SIZE,
                 height: FOOD_SIZE,
-                color: FOOD_COLOR,
+                color: kind.color,
             },
             position,
+            expires: kind
+                .lifetime
+                .map(|seconds| Timer::from_seconds(seconds, TimerMode::Once)),
+            kind,
         }
     }
-}
 
-// Methods
-impl Food {
+    /// Food of a random kind on a free cell, the level may narrow the cells down
+    pub fn rand<R: Rng>(
+        level: Option<&Level>,
+        types: &FoodTypes,
+        occupancy: &Occupancy,
+        rng: &mut R,
+    ) -> Option<Self> {
+        let position: Position = match level {
+            Some(level) => level.food_position(rng, occupancy)?,
+            None => occupancy.rand_free(rng)?,
+        };
+
+        Some(Self::new(position, types.choose(rng)))
+    }
+
     pub fn position(&self) -> &Position {
         &self.position
     }
+
+    pub fn kind(&self) -> &FoodKind {
+        &self.kind
+    }
 }
 
 // Systmes
 impl Food {
     pub fn spawn(storage: &mut Storage) {
+        let count: usize = storage.get_all::<Food>().map_or(0, |food| food.len());
+
         // If there is more food - ignore
-        if let Some(food) = storage.get_all::<Food>() {
-            if (food.len()) as u8 >= MAX_FOOD {
-                return ();
+        let max: usize = storage
+            .get_first::<Level>()
+            .map_or(MAX_FOOD as usize, |level| level.food.max);
+        if count >= max {
+            return;
+        }
+
+        let Some(occupancy) = storage.get_first::<Occupancy>().cloned() else {
+            return;
+        };
+        let types: FoodTypes = storage
+            .get_first::<FoodTypes>()
+            .cloned()
+            .unwrap_or_default();
+
+        let food: Option<Food> = match storage.get_several_mut::<Level, GameRng>() {
+            Some((level, mut rng)) => Food::rand(Some(level[0]), &types, &occupancy, rng[0].rng()),
+            None => Food::rand(
+                storage.get_first::<Level>(),
+                &types,
+                &occupancy,
+                &mut rand::thread_rng(),
+            ),
+        };
+
+        match food {
+            Some(food) => {
+                storage.spawn(food);
             }
+            // Nothing left to eat and nowhere to put it
+            None if count == 0 && occupancy.is_full() => GameOver::win(storage),
+            // Food cells are taken for now, the snake moves on
+            None => {}
         }
+    }
+
+    pub fn expire(storage: &mut Storage) {
+        let Some(delta) = storage.get_first::<Time>().map(Time::delta) else {
+            return;
+        };
 
-        storage.add(Food::default());
+        let expired: Vec<usize> = match storage.get_all_mut::<Food>() {
+            Some(mut food) => food
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(index, f)| {
+                    let timer: &mut Timer = f.expires.as_mut()?;
+
+                    timer.tick(delta).finished().then_some(index)
+                })
+                .collect(),
+            None => return,
+        };
+
+        // From the back, removing swaps the last food into the hole
+        for index in expired.into_iter().rev() {
+            storage.remove::<Food>(index);
+        }
     }
 
     pub fn draw(storage: &mut Storage) {
@@ -64,6 +242,53 @@ impl Food {
     }
 }
 
+pub fn load_food_types(storage: &mut Storage) -> Result<(), FoodTypesError> {
+    // Replays bring their own
+    if storage.get_first::<FoodTypes>().is_some() {
+        return Ok(());
+    }
+
+    let types: FoodTypes = match FoodTypes::load(FOOD_TYPES) {
+        Ok(types) => types,
+        Err(FoodTypesError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+            FoodTypes::default()
+        }
+        Err(err) => return Err(err),
+    };
+
+    storage.add(types);
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum FoodTypesError {
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for FoodTypesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoodTypesError::Io(err) => write!(f, "{err}"),
+            FoodTypesError::Format(err) => write!(f, "invalid food types: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FoodTypesError {}
+
+impl From<io::Error> for FoodTypesError {
+    fn from(err: io::Error) -> Self {
+        FoodTypesError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FoodTypesError {
+    fn from(err: serde_json::Error) -> Self {
+        FoodTypesError::Format(err)
+    }
+}
+
 // TODO: Clean up this crap after adding macros to the repository...
 
 impl Component for Food {
@@ -75,3 +300,150 @@ impl Component for Food {
         self
     }
 }
+
+impl Component for FoodTypes {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Effect, Food, FoodKind, FoodTypes};
+    use crate::{
+        cfg::FOOD_TYPES,
+        game::Position,
+        game_over::{GameState, Score},
+        level::Level,
+        occupancy::Occupancy,
+        player::Player,
+        testing::{game_without_window, headless_app},
+    };
+    use core::{registry::Prefab, App, Entity, ScriptedInput, Storage};
+    use macroquad::prelude::KeyCode;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn kind(name: &str, weight: u32) -> FoodKind {
+        FoodKind {
+            name: name.to_string(),
+            weight,
+            ..FoodKind::default()
+        }
+    }
+
+    #[test]
+    fn kinds_are_picked_by_weight() {
+        let types: FoodTypes =
+            FoodTypes(vec![kind("normal", 3), kind("never", 0), kind("rare", 1)]);
+        let mut rng: StdRng = StdRng::seed_from_u64(0);
+        let picks: Vec<String> = (0..400).map(|_| types.choose(&mut rng).name).collect();
+        let rare: usize = picks.iter().filter(|name| *name == "rare").count();
+
+        assert!(!picks.iter().any(|name| name == "never"));
+        assert!((60..140).contains(&rare));
+    }
+
+    #[test]
+    fn normal_food_without_weights() {
+        let types: FoodTypes = FoodTypes(vec![kind("never", 0)]);
+
+        assert_eq!(
+            types.choose(&mut StdRng::seed_from_u64(0)),
+            FoodKind::default()
+        );
+        assert_eq!(
+            FoodTypes(Vec::new()).choose(&mut StdRng::seed_from_u64(0)),
+            FoodKind::default()
+        );
+    }
+
+    #[test]
+    fn food_types_file_parses() {
+        let types: FoodTypes = FoodTypes::load("config/food.json").unwrap();
+
+        assert_eq!(
+            types.get("normal").map(|kind| kind.effect),
+            Some(Effect::Grow(1))
+        );
+        assert!(types.0.iter().any(|kind| kind.lifetime.is_some()));
+    }
+
+    #[test]
+    fn food_prefab_spawns_at_the_same_cell() {
+        let mut app: App = game_without_window();
+        let prefab: Prefab = app.storage().registry().prefab("food").unwrap();
+        let storage: &mut Storage = app.storage_mut();
+
+        let entities: Vec<Entity> = (0..2)
+            .map(|_| {
+                let entity: Entity = storage.spawn_empty();
+                prefab.insert(storage, entity);
+                entity
+            })
+            .collect();
+
+        let positions: Vec<Position> = entities
+            .iter()
+            .map(|entity| *storage.get_component::<Food>(*entity).unwrap().position())
+            .collect();
+
+        assert_eq!(positions[0], positions[1]);
+    }
+
+    #[test]
+    fn golden_food_scores_its_value() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(1, KeyCode::D);
+
+        let mut app: App = headless_app(input);
+        let golden: FoodKind = FoodTypes::load(FOOD_TYPES)
+            .unwrap()
+            .get("golden")
+            .cloned()
+            .unwrap();
+        app.storage_mut().spawn(Food::new(Position(2, 15), golden));
+
+        for _ in 0..4 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert_eq!(app.storage().get_first::<Score>(), Some(&Score(5)));
+        assert_eq!(snake.length(), 2);
+    }
+
+    #[test]
+    fn covered_food_cell_only_delays_spawning() {
+        let mut app: App = headless_app(ScriptedInput::new());
+        app.storage_mut()
+            .add("topology: toroidal\n---\n>...*.".parse::<Level>().unwrap());
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        let occupancy: &Occupancy = app.storage().get_first::<Occupancy>().unwrap();
+
+        // The tail sits on the only food cell, the rest of the board is still free
+        assert_eq!(occupancy.free_count(), 4);
+        assert_eq!(app.storage().count::<Food>(), 0);
+        assert_eq!(
+            app.storage().get_first::<GameState>(),
+            Some(&GameState::Playing)
+        );
+
+        app.update();
+
+        // Spawned again once the tail moved on
+        assert_eq!(app.storage().count::<Food>(), 1);
+        assert_eq!(
+            app.storage().get_first::<GameState>(),
+            Some(&GameState::Playing)
+        );
+    }
+}
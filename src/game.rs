@@ -1,17 +1,36 @@
-use crate::{cfg::CELL_SIZE, food::Food, player::Player};
-use core::{storage::Component, Plugin, PluginBuilder};
-use rand::Rng;
+use crate::{
+    board::Board, cfg::CELL_SIZE, food::Food, game_over::GameOver, input::InputPlugin,
+    level::LevelPlugin, obstacle::Obstacle, occupancy::Occupancy, player::Player,
+    settings::Settings,
+};
+use core::{storage::Component, Plugin, PluginBuilder, UniquePolicy};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::{any::Any, ops::Range};
 
 pub struct Game;
 
 impl Plugin for Game {
     fn new(builder: &mut PluginBuilder) {
-        builder.add_plugin::<Player>().add_plugin::<Food>();
+        builder
+            .register_component::<Position>()
+            // Restarts spawn the level's board again
+            .register_component::<Board>()
+            .register_unique::<Board>(UniquePolicy::Replace)
+            // Actions are updated before the systems reading them
+            .add_plugin::<InputPlugin>()
+            .add_plugin::<LevelPlugin>()
+            .add_plugin::<Player>()
+            .add_plugin::<Obstacle>()
+            // Rebuilt after the snake has moved, before food is spawned
+            .add_plugin::<Occupancy>()
+            .add_plugin::<Food>()
+            .add_plugin::<GameOver>()
+            .add_plugin::<Settings>();
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
 pub struct Position(pub i32, pub i32);
 
 impl Position {
@@ -19,9 +38,7 @@ impl Position {
         value as f32 * CELL_SIZE.floor()
     }
 
-    pub fn rand(x: Range<i32>, y: Range<i32>) -> Self {
-        let mut rng = rand::thread_rng();
-
+    pub fn rand<R: Rng>(rng: &mut R, x: Range<i32>, y: Range<i32>) -> Self {
         // Random position at grid
         let x = rng.gen_range(x);
         let y = rng.gen_range(y);
@@ -30,6 +47,36 @@ impl Position {
     }
 }
 
+// Source of every random decision of the game, seeded to be replayable
+#[derive(Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
 // TODO: Clean up this crap after adding macros to the repository...
 
 impl Component for Position {
@@ -41,3 +88,13 @@ impl Component for Position {
         self
     }
 }
+
+impl Component for GameRng {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,393 @@
+use macroquad::prelude::{draw_rectangle, draw_text, measure_text, Color, BLACK, WHITE};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, fmt};
+use tracing::info;
+
+use crate::{
+    cfg::{GAME_OVER, GRID_SIZE, VICTORY},
+    food::Food,
+    input::{Action, ActionState, Keybindings},
+    level::Level,
+    obstacle::Obstacle,
+    player::Player,
+};
+use core::{
+    storage::Component, system::SystemId, Entity, Plugin, PluginBuilder, Storage, UniquePolicy,
+};
+
+const FONT_SIZE: f32 = 24.;
+const LINE_HEIGHT: f32 = 28.;
+const BACKGROUND: Color = Color::new(BLACK.r, BLACK.g, BLACK.b, 0.6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeathCause {
+    OutOfBounds,
+    Cannibalism,
+    Obstacle,
+}
+
+impl fmt::Display for DeathCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeathCause::OutOfBounds => write!(f, "Your snake is out of bounds"),
+            DeathCause::Cannibalism => write!(f, "Your snake has eaten its tail"),
+            DeathCause::Obstacle => write!(f, "Your snake has hit a wall"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Death {
+    pub cause: DeathCause,
+    pub score: u32,
+    pub length: usize,
+}
+
+// The snake fills every cell which isn't a wall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Victory {
+    pub score: u32,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameState {
+    #[default]
+    Playing,
+    Over(Death),
+    Won(Victory),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score(pub u32);
+
+// Systems paused while the game is over or the settings are open, added by their plugins
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Simulation(pub Vec<SystemId>);
+
+impl Simulation {
+    /// Pauses the systems while the game is over
+    pub fn add(builder: &mut PluginBuilder, ids: Vec<SystemId>) {
+        builder.add_startup_system(move |storage: &mut Storage| {
+            match storage.get_first_mut::<Simulation>() {
+                Some(simulation) => simulation.0.extend(ids.iter().copied()),
+                None => {
+                    storage.add(Simulation(ids.clone()));
+                }
+            }
+        });
+    }
+
+    /// Pauses or resumes every system, from the next update
+    pub fn set_running(storage: &mut Storage, running: bool) {
+        let ids: Vec<SystemId> = storage
+            .get_first::<Simulation>()
+            .map_or(Vec::new(), |simulation| simulation.0.clone());
+
+        for id in ids {
+            match running {
+                true => storage.commands().enable(id),
+                false => storage.commands().disable(id),
+            };
+        }
+    }
+}
+
+// Stops the game when the snake dies or wins and restarts it on demand
+pub struct GameOver;
+
+impl Plugin for GameOver {
+    fn new(builder: &mut PluginBuilder) {
+        builder
+            .register_unique::<GameState>(UniquePolicy::Replace)
+            .register_unique::<Score>(UniquePolicy::Replace);
+        builder.add_startup_system(GameOver::init);
+        builder.add_system(GameOver::controls);
+        builder.add_system(GameOver::draw);
+    }
+}
+
+// Methods
+impl GameOver {
+    pub fn die(storage: &mut Storage, cause: DeathCause) {
+        if storage.get_first::<GameState>() != Some(&GameState::Playing) {
+            return;
+        }
+
+        let death: Death = Death {
+            cause,
+            score: storage.get_first::<Score>().map_or(0, |score| score.0),
+            length: storage.get_first::<Player>().map_or(0, Player::length),
+        };
+
+        Self::end(storage, GameState::Over(death));
+    }
+
+    pub fn win(storage: &mut Storage) {
+        if storage.get_first::<GameState>() != Some(&GameState::Playing) {
+            return;
+        }
+
+        let victory: Victory = Victory {
+            score: storage.get_first::<Score>().map_or(0, |score| score.0),
+            length: storage.get_first::<Player>().map_or(0, Player::length),
+        };
+
+        Self::end(storage, GameState::Won(victory));
+    }
+
+    fn end(storage: &mut Storage, state: GameState) {
+        match state {
+            GameState::Over(death) => info!(
+                cause = %death.cause,
+                score = death.score,
+                length = death.length,
+                "game over"
+            ),
+            GameState::Won(victory) => {
+                info!(score = victory.score, length = victory.length, "game won")
+            }
+            GameState::Playing => {}
+        }
+
+        storage.add(state);
+        Simulation::set_running(storage, false);
+    }
+
+    /// Respawns the level and resets the score, settings and input are kept
+    pub fn restart(storage: &mut Storage) {
+        let Some(level) = storage.get_first::<Level>().cloned() else {
+            return;
+        };
+        let entities: Vec<Entity> = storage
+            .entities()
+            .filter(|entity| {
+                storage.get_component::<Player>(*entity).is_some()
+                    || storage.get_component::<Food>(*entity).is_some()
+                    || storage.get_component::<Obstacle>(*entity).is_some()
+            })
+            .collect();
+
+        for entity in entities {
+            storage.despawn(entity);
+        }
+
+        level.spawn(storage);
+        storage.add(Score::default()).add(GameState::Playing);
+        Simulation::set_running(storage, true);
+        info!("game restarted");
+    }
+}
+
+// Systems
+impl GameOver {
+    pub fn init(storage: &mut Storage) {
+        storage.add(Score::default()).add(GameState::Playing);
+    }
+
+    pub fn controls(storage: &mut Storage) {
+        if storage.get_first::<GameState>() == Some(&GameState::Playing) {
+            return;
+        }
+
+        let Some(actions) = storage
+            .get_first::<ActionState>()
+            .map(|state| state.player(0))
+        else {
+            return;
+        };
+
+        let (restart, quit): (bool, bool) = (
+            actions.just_pressed(Action::Confirm),
+            actions.just_pressed(Action::Pause),
+        );
+
+        if quit {
+            storage.commands().stop();
+        } else if restart {
+            Self::restart(storage);
+        }
+    }
+
+    pub fn draw(storage: &mut Storage) {
+        let (title, reason, score, length): (&str, String, u32, usize) =
+            match storage.get_first::<GameState>().copied() {
+                Some(GameState::Over(death)) => (
+                    GAME_OVER,
+                    death.cause.to_string(),
+                    death.score,
+                    death.length,
+                ),
+                Some(GameState::Won(victory)) => (
+                    VICTORY,
+                    "The board is full".to_string(),
+                    victory.score,
+                    victory.length,
+                ),
+                _ => return,
+            };
+
+        let keybindings: Keybindings = storage
+            .get_first::<Keybindings>()
+            .cloned()
+            .unwrap_or_default();
+        let key = |action: Action| match keybindings.keys(0, action).first() {
+            Some(key) => format!("{:?}", key.0),
+            None => "-".to_string(),
+        };
+
+        let lines: [String; 5] = [
+            title.to_string(),
+            reason,
+            format!("Score: {score}"),
+            format!("Length: {length}"),
+            format!(
+                "{} - restart, {} - quit",
+                key(Action::Confirm),
+                key(Action::Pause)
+            ),
+        ];
+
+        draw_overlay(&lines);
+    }
+}
+
+/// Centered lines over the dimmed board
+pub fn draw_overlay(lines: &[String]) {
+    draw_rectangle(0., 0., GRID_SIZE, GRID_SIZE, BACKGROUND);
+
+    let top: f32 = (GRID_SIZE - lines.len() as f32 * LINE_HEIGHT) / 2.;
+
+    for (index, line) in lines.iter().enumerate() {
+        let width: f32 = measure_text(line, None, FONT_SIZE as u16, 1.).width;
+
+        draw_text(
+            line,
+            (GRID_SIZE - width) / 2.,
+            top + (index + 1) as f32 * LINE_HEIGHT,
+            FONT_SIZE,
+            WHITE,
+        );
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for GameState {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Simulation {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Score {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeathCause, GameState, Simulation, Victory};
+    use crate::{
+        food::Food,
+        game::Position,
+        level::Level,
+        player::{Direction, Player},
+        testing::headless_app,
+    };
+    use core::{App, ScriptedInput};
+    use macroquad::prelude::KeyCode;
+
+    #[test]
+    fn leaving_the_board_ends_the_game_and_confirm_restarts_it() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(1, KeyCode::A).press(5, KeyCode::Enter);
+
+        let mut app: App = headless_app(input);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert!(!app.is_stopped());
+        assert!(matches!(
+            app.storage().get_first::<GameState>(),
+            Some(GameState::Over(death)) if death.cause == DeathCause::OutOfBounds
+        ));
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert_eq!(app.storage().count::<Player>(), 1);
+        assert_eq!(
+            app.storage().get_first::<GameState>(),
+            Some(&GameState::Playing)
+        );
+        assert_eq!(snake.position(), Position(0, 15));
+        assert_eq!(snake.direction(), Direction::None);
+
+        // Controls, steps, food spawning and expiry run again
+        let simulation: Simulation = app.storage().get_first::<Simulation>().unwrap().clone();
+
+        assert_eq!(simulation.0.len(), 4);
+        assert!(simulation
+            .0
+            .iter()
+            .all(|id| app.scheduler_mut().is_enabled(*id)));
+    }
+
+    #[test]
+    fn filling_the_board_wins_the_game() {
+        let mut app: App = headless_app(ScriptedInput::new());
+        app.storage_mut()
+            .add("topology: toroidal\n---\n>.".parse::<Level>().unwrap());
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        assert_eq!(app.storage().count::<Food>(), 0);
+        assert_eq!(
+            app.storage().get_first::<GameState>(),
+            Some(&GameState::Won(Victory {
+                score: 1,
+                length: 2
+            }))
+        );
+    }
+
+    #[test]
+    fn quit_stops_the_app_after_game_over() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(1, KeyCode::A).press(5, KeyCode::Escape);
+
+        let mut app: App = headless_app(input);
+
+        for _ in 0..7 {
+            app.update();
+        }
+
+        assert!(app.is_stopped());
+    }
+}
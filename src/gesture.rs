@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cfg::{CELL_SIZE, SWIPE_THRESHOLD, TAP_DEAD_ZONE, TAP_TOLERANCE},
+    game::Position,
+    input::{Action, ActionState, Keybindings},
+    player::{Direction, Player},
+};
+use core::{scheduler::Local, Input, PointerEvent, PointerPhase, Storage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GestureSettings {
+    // Distance a drag has to cover to turn the snake
+    pub swipe_threshold: f32,
+    // Farthest the pointer may move between press and release of a tap
+    pub tap_tolerance: f32,
+    // Taps this close to the head are ignored
+    pub dead_zone: f32,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            swipe_threshold: SWIPE_THRESHOLD,
+            tap_tolerance: TAP_TOLERANCE,
+            dead_zone: TAP_DEAD_ZONE,
+        }
+    }
+}
+
+// Turns swipes and taps relative to the head into directions
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+    start: Option<(f32, f32)>,
+    swiped: bool,
+}
+
+impl GestureRecognizer {
+    pub fn update(
+        &mut self,
+        event: &PointerEvent,
+        head: (f32, f32),
+        settings: &GestureSettings,
+    ) -> Option<Direction> {
+        match event.phase {
+            PointerPhase::Pressed => {
+                self.start = Some(event.position);
+                self.swiped = false;
+                None
+            }
+            // Long drags turn again from where the last swipe ended
+            PointerPhase::Moved => {
+                let delta: (f32, f32) = offset(self.start?, event.position);
+
+                if length(delta) < settings.swipe_threshold {
+                    return None;
+                }
+
+                self.start = Some(event.position);
+                self.swiped = true;
+                direction(delta)
+            }
+            PointerPhase::Released => {
+                let delta: (f32, f32) = offset(self.start.take()?, event.position);
+
+                if length(delta) >= settings.swipe_threshold {
+                    return direction(delta);
+                }
+
+                if self.swiped || length(delta) > settings.tap_tolerance {
+                    return None;
+                }
+
+                let tap: (f32, f32) = offset(head, event.position);
+
+                match tap.0.abs().max(tap.1.abs()) <= settings.dead_zone {
+                    true => None,
+                    false => direction(tap),
+                }
+            }
+        }
+    }
+}
+
+pub fn update_gestures(storage: &mut Storage, recognizer: &mut Local<GestureRecognizer>) {
+    let settings: GestureSettings = storage
+        .get_first::<Keybindings>()
+        .map(|keybindings| keybindings.gestures)
+        .unwrap_or_default();
+
+    let (Some(input), Some(snake)) = (storage.get_first::<Input>(), storage.get_first::<Player>())
+    else {
+        return;
+    };
+
+    let Position(x, y) = snake.position();
+    let head: (f32, f32) = (
+        Position::compute(x) + CELL_SIZE / 2.,
+        Position::compute(y) + CELL_SIZE / 2.,
+    );
+
+    let actions: Vec<Action> = input
+        .pointer_events()
+        .iter()
+        .filter_map(|event| recognizer.update(event, head, &settings))
+        .filter_map(action)
+        .collect();
+
+    if let Some(state) = storage.get_first_mut::<ActionState>() {
+        actions
+            .into_iter()
+            .for_each(|action| state.trigger(0, action));
+    }
+}
+
+fn action(direction: Direction) -> Option<Action> {
+    match direction {
+        Direction::Top => Some(Action::MoveUp),
+        Direction::Down => Some(Action::MoveDown),
+        Direction::Left => Some(Action::MoveLeft),
+        Direction::Right => Some(Action::MoveRight),
+        Direction::None => None,
+    }
+}
+
+fn offset(from: (f32, f32), to: (f32, f32)) -> (f32, f32) {
+    (to.0 - from.0, to.1 - from.1)
+}
+
+fn length((x, y): (f32, f32)) -> f32 {
+    x.hypot(y)
+}
+
+// The dominant axis wins, exact diagonals are ambiguous
+fn direction((x, y): (f32, f32)) -> Option<Direction> {
+    if x.abs() > y.abs() {
+        Some(match x > 0. {
+            true => Direction::Right,
+            false => Direction::Left,
+        })
+    } else if y.abs() > x.abs() {
+        Some(match y > 0. {
+            true => Direction::Down,
+            false => Direction::Top,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GestureRecognizer, GestureSettings};
+    use crate::player::Direction;
+    use core::{PointerEvent, PointerPhase};
+
+    const HEAD: (f32, f32) = (100., 100.);
+
+    fn settings() -> GestureSettings {
+        GestureSettings {
+            swipe_threshold: 20.,
+            tap_tolerance: 5.,
+            dead_zone: 10.,
+        }
+    }
+
+    fn gesture(events: &[(PointerPhase, (f32, f32))]) -> Vec<Direction> {
+        let mut recognizer: GestureRecognizer = GestureRecognizer::default();
+
+        events
+            .iter()
+            .filter_map(|(phase, position)| {
+                let event: PointerEvent = PointerEvent {
+                    phase: *phase,
+                    position: *position,
+                };
+
+                recognizer.update(&event, HEAD, &settings())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn swipe_turns_once_the_threshold_is_crossed() {
+        let directions: Vec<Direction> = gesture(&[
+            (PointerPhase::Pressed, (0., 0.)),
+            (PointerPhase::Moved, (10., 2.)),
+            (PointerPhase::Moved, (25., 3.)),
+            (PointerPhase::Released, (30., 3.)),
+        ]);
+
+        assert_eq!(directions, [Direction::Right]);
+    }
+
+    #[test]
+    fn long_drag_turns_several_times() {
+        let directions: Vec<Direction> = gesture(&[
+            (PointerPhase::Pressed, (0., 0.)),
+            (PointerPhase::Moved, (0., -25.)),
+            (PointerPhase::Moved, (-25., -25.)),
+            (PointerPhase::Released, (-25., -25.)),
+        ]);
+
+        assert_eq!(directions, [Direction::Top, Direction::Left]);
+    }
+
+    #[test]
+    fn fast_swipe_is_recognized_on_release() {
+        let directions: Vec<Direction> = gesture(&[
+            (PointerPhase::Pressed, (50., 50.)),
+            (PointerPhase::Released, (50., 90.)),
+        ]);
+
+        assert_eq!(directions, [Direction::Down]);
+    }
+
+    #[test]
+    fn short_drag_is_neither_a_swipe_nor_a_tap() {
+        let directions: Vec<Direction> = gesture(&[
+            (PointerPhase::Pressed, (0., 0.)),
+            (PointerPhase::Moved, (12., 0.)),
+            (PointerPhase::Released, (12., 0.)),
+        ]);
+
+        assert!(directions.is_empty());
+    }
+
+    #[test]
+    fn tap_turns_towards_the_pointer() {
+        assert_eq!(
+            gesture(&[
+                (PointerPhase::Pressed, (40., 110.)),
+                (PointerPhase::Released, (42., 110.)),
+            ]),
+            [Direction::Left]
+        );
+        assert_eq!(
+            gesture(&[
+                (PointerPhase::Pressed, (105., 300.)),
+                (PointerPhase::Released, (105., 300.)),
+            ]),
+            [Direction::Down]
+        );
+    }
+
+    #[test]
+    fn tap_in_the_dead_zone_is_ignored() {
+        let directions: Vec<Direction> = gesture(&[
+            (PointerPhase::Pressed, (108., 95.)),
+            (PointerPhase::Released, (108., 95.)),
+        ]);
+
+        assert!(directions.is_empty());
+    }
+
+    #[test]
+    fn release_without_press_is_ignored() {
+        let directions: Vec<Direction> = gesture(&[(PointerPhase::Released, (0., 0.))]);
+
+        assert!(directions.is_empty());
+    }
+}
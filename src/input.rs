@@ -0,0 +1,623 @@
+use macroquad::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cfg::KEYBINDINGS,
+    gesture::{update_gestures, GestureSettings},
+};
+use core::{storage::Component, Input, Plugin, PluginBuilder, Storage};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    Confirm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Key(#[serde(with = "KeyCodeDef")] pub KeyCode);
+
+pub type Bindings = BTreeMap<Action, Vec<Key>>;
+
+// File the keybindings were loaded from, rebound keys are saved to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeybindingsFile(pub PathBuf);
+
+// Keys of every local player, the first one is also controlled by the pointer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keybindings {
+    pub players: Vec<Bindings>,
+    #[serde(default)]
+    pub gestures: GestureSettings,
+}
+
+impl Keybindings {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, KeybindingsError> {
+        let data: String = fs::read_to_string(path)?;
+
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), KeybindingsError> {
+        let data: String = serde_json::to_string_pretty(self)?;
+
+        Ok(fs::write(path, data)?)
+    }
+
+    pub fn keys(&self, player: usize, action: Action) -> &[Key] {
+        self.players
+            .get(player)
+            .and_then(|bindings| bindings.get(&action))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds the key to the action, taking it from the other actions of the player
+    pub fn bind(&mut self, player: usize, action: Action, key: Key) -> &mut Self {
+        if self.players.len() <= player {
+            self.players.resize_with(player + 1, Bindings::new);
+        }
+
+        let bindings: &mut Bindings = &mut self.players[player];
+
+        bindings
+            .values_mut()
+            .for_each(|keys| keys.retain(|bound| *bound != key));
+        bindings.entry(action).or_default().push(key);
+
+        self
+    }
+
+    /// Replaces every key of the action
+    pub fn rebind(&mut self, player: usize, action: Action, key: Key) -> &mut Self {
+        self.unbind(player, action).bind(player, action, key)
+    }
+
+    pub fn unbind(&mut self, player: usize, action: Action) -> &mut Self {
+        if let Some(bindings) = self.players.get_mut(player) {
+            bindings.remove(&action);
+        }
+
+        self
+    }
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        let bindings: Bindings = [
+            (Action::MoveUp, [KeyCode::W, KeyCode::Up]),
+            (Action::MoveDown, [KeyCode::S, KeyCode::Down]),
+            (Action::MoveLeft, [KeyCode::A, KeyCode::Left]),
+            (Action::MoveRight, [KeyCode::D, KeyCode::Right]),
+            (Action::Pause, [KeyCode::Escape, KeyCode::P]),
+            (Action::Confirm, [KeyCode::Enter, KeyCode::Space]),
+        ]
+        .into_iter()
+        .map(|(action, keys)| (action, keys.into_iter().map(Key).collect()))
+        .collect();
+
+        Self {
+            players: vec![bindings],
+            gestures: GestureSettings::default(),
+        }
+    }
+}
+
+// Actions of one player during the current frame
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    pressed: BTreeSet<Action>,
+    just_pressed: BTreeSet<Action>,
+    just_released: BTreeSet<Action>,
+}
+
+impl Actions {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+}
+
+static NO_ACTIONS: Actions = Actions {
+    pressed: BTreeSet::new(),
+    just_pressed: BTreeSet::new(),
+    just_released: BTreeSet::new(),
+};
+
+// Systems read actions instead of keys
+#[derive(Debug, Default)]
+pub struct ActionState {
+    players: Vec<Actions>,
+}
+
+impl ActionState {
+    pub fn player(&self, player: usize) -> &Actions {
+        self.players.get(player).unwrap_or(&NO_ACTIONS)
+    }
+
+    /// Presses the action for this update, for input which isn't bound to keys
+    pub fn trigger(&mut self, player: usize, action: Action) {
+        if self.players.len() <= player {
+            self.players.resize_with(player + 1, Actions::default);
+        }
+
+        self.players[player].just_pressed.insert(action);
+    }
+}
+
+// Binds the next pressed key to the action, added by the settings menu on confirm
+#[derive(Debug, Clone, Copy)]
+pub struct Rebind {
+    pub player: usize,
+    pub action: Action,
+}
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn new(builder: &mut PluginBuilder) {
+        builder.add_startup_system(load_keybindings);
+        builder.add_system(capture_rebind);
+        builder.add_system(update_actions);
+        builder.add_system(update_gestures);
+    }
+}
+
+// Systems
+pub fn load_keybindings(storage: &mut Storage) -> Result<(), KeybindingsError> {
+    // Replays bring their own
+    if storage.get_first::<Keybindings>().is_some() {
+        storage.add(ActionState::default());
+        return Ok(());
+    }
+
+    let keybindings: Keybindings = match Keybindings::load(KEYBINDINGS) {
+        Ok(keybindings) => keybindings,
+        Err(KeybindingsError::Io(err)) if err.kind() == io::ErrorKind::NotFound => {
+            Keybindings::default()
+        }
+        Err(err) => return Err(err),
+    };
+
+    storage
+        .add(keybindings)
+        .add(KeybindingsFile(PathBuf::from(KEYBINDINGS)))
+        .add(ActionState::default());
+    Ok(())
+}
+
+pub fn capture_rebind(storage: &mut Storage) -> Result<(), KeybindingsError> {
+    let Some(rebind) = storage.get_first::<Rebind>().copied() else {
+        return Ok(());
+    };
+
+    let Some(key) = storage.get_first::<Input>().and_then(Input::last_pressed) else {
+        return Ok(());
+    };
+
+    storage.remove::<Rebind>(0);
+
+    if let Some(keybindings) = storage.get_first_mut::<Keybindings>() {
+        keybindings.rebind(rebind.player, rebind.action, Key(key));
+    }
+
+    // Replays bring their own keybindings and leave the file alone
+    match (
+        storage.get_first::<Keybindings>(),
+        storage.get_first::<KeybindingsFile>(),
+    ) {
+        (Some(keybindings), Some(file)) => keybindings.save(&file.0),
+        _ => Ok(()),
+    }
+}
+
+pub fn update_actions(storage: &mut Storage) {
+    let (Some(keybindings), Some(input)) = (
+        storage.get_first::<Keybindings>(),
+        storage.get_first::<Input>(),
+    ) else {
+        return;
+    };
+
+    let players: Vec<Actions> = keybindings
+        .players
+        .iter()
+        .map(|bindings| {
+            let mut actions: Actions = Actions::default();
+
+            for (action, keys) in bindings.iter() {
+                if keys.iter().any(|key| input.pressed(key.0)) {
+                    actions.pressed.insert(*action);
+                }
+
+                if keys.iter().any(|key| input.just_pressed(key.0)) {
+                    actions.just_pressed.insert(*action);
+                }
+
+                if keys.iter().any(|key| input.just_released(key.0)) {
+                    actions.just_released.insert(*action);
+                }
+            }
+
+            actions
+        })
+        .collect();
+
+    if let Some(state) = storage.get_first_mut::<ActionState>() {
+        state.players = players;
+    }
+}
+
+#[derive(Debug)]
+pub enum KeybindingsError {
+    Io(io::Error),
+    Format(serde_json::Error),
+}
+
+impl fmt::Display for KeybindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeybindingsError::Io(err) => write!(f, "{err}"),
+            KeybindingsError::Format(err) => write!(f, "invalid keybindings: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for KeybindingsError {}
+
+impl From<io::Error> for KeybindingsError {
+    fn from(err: io::Error) -> Self {
+        KeybindingsError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for KeybindingsError {
+    fn from(err: serde_json::Error) -> Self {
+        KeybindingsError::Format(err)
+    }
+}
+
+// Mirror of macroquad's key codes for (de)serialization
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "KeyCode")]
+enum KeyCodeDef {
+    Space,
+    Apostrophe,
+    Comma,
+    Minus,
+    Period,
+    Slash,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Semicolon,
+    Equal,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    LeftBracket,
+    Backslash,
+    RightBracket,
+    GraveAccent,
+    World1,
+    World2,
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Insert,
+    Delete,
+    Right,
+    Left,
+    Down,
+    Up,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    CapsLock,
+    ScrollLock,
+    NumLock,
+    PrintScreen,
+    Pause,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    F25,
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpDecimal,
+    KpDivide,
+    KpMultiply,
+    KpSubtract,
+    KpAdd,
+    KpEnter,
+    KpEqual,
+    LeftShift,
+    LeftControl,
+    LeftAlt,
+    LeftSuper,
+    RightShift,
+    RightControl,
+    RightAlt,
+    RightSuper,
+    Menu,
+    Unknown,
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Keybindings {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for ActionState {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for KeybindingsFile {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Rebind {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capture_rebind, Action, ActionState, Key, Keybindings, KeybindingsFile, Rebind};
+    use crate::cfg::{APP_CONFIG, KEYBINDINGS};
+    use core::{App, ScriptedInput};
+    use macroquad::prelude::KeyCode;
+    use std::{fs, path::PathBuf};
+
+    fn keys(codes: &[KeyCode]) -> Vec<Key> {
+        codes.iter().copied().map(Key).collect()
+    }
+
+    #[test]
+    fn bind_adds_keys_and_players() {
+        let mut keybindings: Keybindings = Keybindings::default();
+
+        keybindings.bind(0, Action::MoveUp, Key(KeyCode::K));
+        keybindings.bind(1, Action::Pause, Key(KeyCode::Q));
+
+        assert_eq!(
+            keybindings.keys(0, Action::MoveUp),
+            keys(&[KeyCode::W, KeyCode::Up, KeyCode::K])
+        );
+        assert_eq!(keybindings.players.len(), 2);
+        assert_eq!(keybindings.keys(1, Action::Pause), keys(&[KeyCode::Q]));
+        assert!(keybindings.keys(1, Action::MoveUp).is_empty());
+        assert!(keybindings.keys(5, Action::MoveUp).is_empty());
+    }
+
+    #[test]
+    fn bound_key_is_taken_from_other_actions() {
+        let mut keybindings: Keybindings = Keybindings::default();
+
+        keybindings.bind(0, Action::Pause, Key(KeyCode::W));
+
+        assert_eq!(keybindings.keys(0, Action::MoveUp), keys(&[KeyCode::Up]));
+        assert_eq!(
+            keybindings.keys(0, Action::Pause),
+            keys(&[KeyCode::Escape, KeyCode::P, KeyCode::W])
+        );
+
+        // Other players keep their keys
+        keybindings.bind(1, Action::MoveUp, Key(KeyCode::Up));
+
+        assert_eq!(keybindings.keys(0, Action::MoveUp), keys(&[KeyCode::Up]));
+    }
+
+    #[test]
+    fn rebind_replaces_and_unbind_removes() {
+        let mut keybindings: Keybindings = Keybindings::default();
+
+        keybindings.rebind(0, Action::MoveLeft, Key(KeyCode::D));
+
+        assert_eq!(keybindings.keys(0, Action::MoveLeft), keys(&[KeyCode::D]));
+        assert_eq!(
+            keybindings.keys(0, Action::MoveRight),
+            keys(&[KeyCode::Right])
+        );
+
+        keybindings
+            .unbind(0, Action::MoveLeft)
+            .unbind(3, Action::Pause);
+
+        assert!(keybindings.keys(0, Action::MoveLeft).is_empty());
+        assert_eq!(keybindings.players.len(), 1);
+    }
+
+    #[test]
+    fn keybindings_file_round_trips() {
+        assert_eq!(
+            Keybindings::load(KEYBINDINGS).unwrap(),
+            Keybindings::default()
+        );
+
+        let path: PathBuf = std::env::temp_dir().join(format!(
+            "keybindings-{}-round-trip.json",
+            std::process::id()
+        ));
+        let mut keybindings: Keybindings = Keybindings::default();
+        keybindings.rebind(1, Action::Confirm, Key(KeyCode::Tab));
+
+        keybindings.save(&path).unwrap();
+
+        assert_eq!(Keybindings::load(&path).unwrap(), keybindings);
+
+        fs::write(&path, r#"{ "players": [{ "MoveUp": ["NotAKey"] }] }"#).unwrap();
+
+        assert!(matches!(
+            Keybindings::load(&path),
+            Err(super::KeybindingsError::Format(_))
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    fn rebind_app(input: ScriptedInput) -> App {
+        let mut app: App = App::new(APP_CONFIG);
+
+        app.set_input_backend(input);
+        app.add_system(capture_rebind);
+        app.storage_mut()
+            .add(Keybindings::default())
+            .add(ActionState::default())
+            .add(Rebind {
+                player: 0,
+                action: Action::Pause,
+            });
+
+        app
+    }
+
+    #[test]
+    fn next_pressed_key_is_captured_and_saved() {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("keybindings-{}-capture.json", std::process::id()));
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(2, KeyCode::Q);
+
+        let mut app: App = rebind_app(input);
+        app.storage_mut().add(KeybindingsFile(path.clone()));
+
+        app.update();
+
+        // Nothing pressed yet
+        assert_eq!(app.storage().count::<Rebind>(), 1);
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let keybindings: &Keybindings = app.storage().get_first::<Keybindings>().unwrap();
+
+        assert_eq!(app.storage().count::<Rebind>(), 0);
+        assert_eq!(keybindings.keys(0, Action::Pause), keys(&[KeyCode::Q]));
+        assert_eq!(&Keybindings::load(&path).unwrap(), keybindings);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn captured_key_is_not_saved_without_a_file() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(1, KeyCode::W);
+
+        let mut app: App = rebind_app(input);
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let keybindings: &Keybindings = app.storage().get_first::<Keybindings>().unwrap();
+
+        assert_eq!(keybindings.keys(0, Action::Pause), keys(&[KeyCode::W]));
+        assert_eq!(keybindings.keys(0, Action::MoveUp), keys(&[KeyCode::Up]));
+        assert_eq!(
+            Keybindings::load(KEYBINDINGS).unwrap(),
+            Keybindings::default()
+        );
+    }
+}
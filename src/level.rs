@@ -0,0 +1,406 @@
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    board::{Board, Topology},
+    cfg::MAX_FOOD,
+    game::Position,
+    obstacle::Obstacle,
+    occupancy::Occupancy,
+    player::{Direction, Player},
+};
+use core::{storage::Component, Plugin, PluginBuilder, Storage, UniquePolicy};
+
+// Ends the optional `key: value` header
+const HEADER_END: &str = "---";
+
+// Walls, spawn and food rules of a board, read from a plain-text grid:
+//
+//   name: Corridors
+//   topology: toroidal
+//   max_food: 2
+//   ---
+//   ####..####
+//   #...*....#
+//   #.>......#
+//
+// `#` is a wall, `.` or a space is empty, `*` is a food cell, `@` spawns the
+// snake standing still and `^`, `v`, `<`, `>` spawn it moving
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub board: Board,
+    pub walls: HashSet<Position>,
+    pub spawn: Position,
+    pub direction: Direction,
+    pub food: FoodRules,
+    // Text the level was parsed from, replays keep it
+    source: String,
+}
+
+// Level named by a scene, loaded and spawned at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LevelFile(pub PathBuf);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoodRules {
+    // Food on the board at the same time
+    pub max: usize,
+    // Food only appears on these cells, anywhere off the walls when empty
+    pub cells: Vec<Position>,
+}
+
+impl Default for FoodRules {
+    fn default() -> Self {
+        Self {
+            max: MAX_FOOD as usize,
+            cells: Vec::new(),
+        }
+    }
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LevelError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn is_wall(&self, position: Position) -> bool {
+        self.walls.contains(&position)
+    }
+
+    /// Free cell for new food, none if every allowed cell is taken
+    pub fn food_position<R: Rng>(&self, rng: &mut R, occupancy: &Occupancy) -> Option<Position> {
+        if self.food.cells.is_empty() {
+            return occupancy.rand_free(rng);
+        }
+
+        let free: Vec<Position> = self
+            .food
+            .cells
+            .iter()
+            .filter(|cell| occupancy.is_free(**cell))
+            .copied()
+            .collect();
+
+        free.choose(rng).copied()
+    }
+
+    /// Spawns the board, the walls and the snake
+    pub fn spawn(&self, storage: &mut Storage) {
+        storage.add(self.board);
+
+        for wall in self.walls.iter() {
+            storage.spawn(Obstacle::at(*wall));
+        }
+
+        storage.spawn(Player::at(self.spawn, self.direction));
+    }
+}
+
+impl FromStr for Level {
+    type Err = LevelError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = source.lines().map(str::trim_end).collect();
+        let (header, grid): (&[&str], &[&str]) =
+            match lines.iter().position(|line| *line == HEADER_END) {
+                Some(end) => (&lines[..end], &lines[end + 1..]),
+                None => (&[], &lines),
+            };
+        // Line numbers in errors count from the start of the file
+        let offset: usize = lines.len() - grid.len();
+
+        let mut level: Level = Level {
+            name: String::new(),
+            board: Board::default(),
+            walls: HashSet::new(),
+            spawn: Position(0, 0),
+            direction: Direction::None,
+            food: FoodRules::default(),
+            source: source.to_string(),
+        };
+
+        for (index, line) in header.iter().enumerate() {
+            let invalid = |reason: String| LevelError::Invalid {
+                line: index + 1,
+                reason,
+            };
+
+            if line.is_empty() {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(invalid(format!("expected `key: value`, found `{line}`")));
+            };
+
+            match (key.trim(), value.trim()) {
+                ("name", name) => level.name = name.to_string(),
+                ("topology", "bounded") => level.board.topology = Topology::Bounded,
+                ("topology", "toroidal") => level.board.topology = Topology::Toroidal,
+                ("max_food", max) => {
+                    level.food.max = max
+                        .parse()
+                        .map_err(|_| invalid(format!("`{max}` is not a food count")))?;
+                }
+                (key, value) => return Err(invalid(format!("unknown `{key}: {value}`"))),
+            }
+        }
+
+        let mut spawn: Option<Position> = None;
+
+        for (y, line) in grid.iter().enumerate() {
+            for (x, glyph) in line.chars().enumerate() {
+                let position: Position = Position(x as i32, y as i32);
+                let direction: Direction = match glyph {
+                    '#' => {
+                        level.walls.insert(position);
+                        continue;
+                    }
+                    '*' => {
+                        level.food.cells.push(position);
+                        continue;
+                    }
+                    '.' | ' ' => continue,
+                    '@' => Direction::None,
+                    '^' => Direction::Top,
+                    'v' => Direction::Down,
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    glyph => {
+                        return Err(LevelError::Invalid {
+                            line: offset + y + 1,
+                            reason: format!("unknown cell `{glyph}`"),
+                        })
+                    }
+                };
+
+                if spawn.replace(position).is_some() {
+                    return Err(LevelError::Invalid {
+                        line: offset + y + 1,
+                        reason: "the snake is spawned twice".to_string(),
+                    });
+                }
+
+                level.direction = direction;
+            }
+        }
+
+        level.spawn = spawn.ok_or(LevelError::NoSpawn)?;
+        level.board.width = grid
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        level.board.height = grid.len() as i32;
+
+        Ok(level)
+    }
+}
+
+// Loads and spawns the level before the first update
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn new(builder: &mut PluginBuilder) {
+        builder
+            .register_component::<LevelFile>()
+            .register_unique::<Level>(UniquePolicy::Replace);
+        builder.add_startup_system(load_level);
+    }
+}
+
+// Systems
+pub fn load_level(storage: &mut Storage) -> Result<(), LevelError> {
+    // Replays bring their own
+    let level: Level = match storage.get_first::<Level>() {
+        Some(level) => level.clone(),
+        None => {
+            // Nothing to play until a scene names the level
+            let Some(file) = storage.get_first::<LevelFile>().cloned() else {
+                return Ok(());
+            };
+            let level: Level = Level::load(file.0)?;
+
+            storage.add(level.clone());
+            level
+        }
+    };
+
+    level.spawn(storage);
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Invalid { line: usize, reason: String },
+    NoSpawn,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "{err}"),
+            LevelError::Invalid { line, reason } => {
+                write!(f, "invalid level at line {line}: {reason}")
+            }
+            LevelError::NoSpawn => write!(f, "invalid level: the snake is never spawned"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(err: io::Error) -> Self {
+        LevelError::Io(err)
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for LevelFile {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Component for Level {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, LevelError};
+    use crate::{
+        board::Topology,
+        game::Position,
+        obstacle::Obstacle,
+        occupancy::{Cell, Occupancy},
+        player::{Direction, Player},
+        testing::headless_app,
+    };
+    use core::{App, ScriptedInput};
+
+    fn occupancy(level: &Level) -> Occupancy {
+        let mut occupancy: Occupancy = Occupancy::new(level.board);
+
+        for wall in level.walls.iter() {
+            occupancy.set(*wall, Cell::Obstacle);
+        }
+
+        occupancy
+    }
+
+    #[test]
+    fn grid_without_header() {
+        let level: Level = "#..\n.>*\n".parse().unwrap();
+
+        assert_eq!((level.board.width, level.board.height), (3, 2));
+        assert_eq!(level.board.topology, Topology::Bounded);
+        assert!(level.is_wall(Position(0, 0)));
+        assert_eq!(level.spawn, Position(1, 1));
+        assert_eq!(level.direction, Direction::Right);
+        assert_eq!(level.food.cells, [Position(2, 1)]);
+    }
+
+    #[test]
+    fn header_sets_name_topology_and_food() {
+        let level: Level = "name: Loop\ntopology: toroidal\nmax_food: 3\n---\n@....\n..\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(level.name, "Loop");
+        assert_eq!(level.board.topology, Topology::Toroidal);
+        assert_eq!(level.food.max, 3);
+        // Short rows are padded with empty cells
+        assert_eq!((level.board.width, level.board.height), (5, 2));
+        assert_eq!(level.direction, Direction::None);
+    }
+
+    #[test]
+    fn source_parses_to_the_same_level() {
+        let level: Level = Level::load("levels/level1.lvl").unwrap();
+
+        // Replays play the level from the text they keep
+        assert_eq!(level.source().parse::<Level>().unwrap(), level);
+    }
+
+    #[test]
+    fn invalid_levels_report_the_line() {
+        assert!(matches!(
+            "speed: 3\n---\n@".parse::<Level>(),
+            Err(LevelError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!(
+            "---\n...\n.x.".parse::<Level>(),
+            Err(LevelError::Invalid { line: 3, .. })
+        ));
+        assert!(matches!(
+            "@.>".parse::<Level>(),
+            Err(LevelError::Invalid { line: 1, .. })
+        ));
+        assert!(matches!("#..".parse::<Level>(), Err(LevelError::NoSpawn)));
+    }
+
+    #[test]
+    fn food_avoids_walls_and_taken_cells() {
+        let level: Level = "###\n#@.\n###".parse().unwrap();
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..16 {
+            assert!(matches!(
+                level.food_position(&mut rng, &occupancy(&level)),
+                Some(Position(1 | 2, 1))
+            ));
+        }
+
+        let level: Level = "*@*".parse().unwrap();
+        let mut occupancy: Occupancy = occupancy(&level);
+        occupancy.set(Position(0, 0), Cell::Food);
+
+        assert_eq!(
+            level.food_position(&mut rng, &occupancy),
+            Some(Position(2, 0))
+        );
+
+        occupancy.set(Position(2, 0), Cell::Snake);
+
+        assert_eq!(level.food_position(&mut rng, &occupancy), None);
+    }
+
+    #[test]
+    fn scene_names_the_level() {
+        let mut app: App = headless_app(ScriptedInput::new());
+        app.update();
+
+        let level: &Level = app.storage().get_first::<Level>().unwrap();
+
+        assert_eq!(level.name, "Two bars");
+        assert_eq!(app.storage().count::<Obstacle>(), level.walls.len());
+        assert_eq!(app.storage().count::<Player>(), 1);
+    }
+}
@@ -1,12 +1,25 @@
 use cfg::{WINDOW_HEIGHT, WINDOW_TITLE, WINDOW_WIDTH};
 use macroquad::{prelude::Color, shapes::draw_rectangle, window::Conf};
+use serde::{Deserialize, Serialize};
 
 pub use game::Game;
 
+pub mod board;
 pub mod cfg;
 pub mod food;
 pub mod game;
+pub mod game_over;
+pub mod gesture;
+pub mod input;
+pub mod level;
+pub mod obstacle;
+pub mod occupancy;
 pub mod player;
+pub mod replay;
+pub mod settings;
+
+#[cfg(test)]
+mod testing;
 
 pub fn window_config() -> Conf {
     Conf {
@@ -22,15 +35,26 @@ pub trait Shape: Send + 'static {
     fn draw(&self);
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
     pub width: f32,
     pub height: f32,
+    #[serde(with = "ColorDef")]
     pub color: Color,
 }
 
+// Mirror of macroquad's color for (de)serialization
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Color")]
+struct ColorDef {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+
 impl Rect {
     pub const fn new(x: f32, y: f32, width: f32, height: f32, color: Color) -> Self {
         Self {
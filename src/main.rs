@@ -1,7 +1,36 @@
-use core::App;
-use snake::{cfg::APP_CONFIG, window_config, Game};
+use core::{App, InspectPlugin, LogPlugin};
+use snake::{
+    cfg::APP_CONFIG,
+    replay::{self, Mode},
+    window_config, Game,
+};
+use std::env;
 
 #[macroquad::main(window_config)]
 async fn main() {
-    App::new(APP_CONFIG).add_plugin::<Game>().run().await;
+    let mode: Mode = match Mode::from_args(env::args().skip(1)) {
+        Ok(mode) => mode,
+        Err(err) => {
+            eprintln!("{err}\nUsage: snake [--record <file> | --replay <file>]");
+            return;
+        }
+    };
+
+    let mut app: App = App::new(APP_CONFIG);
+
+    app.add_plugin::<LogPlugin>()
+        .add_plugin::<Game>()
+        .add_plugin::<InspectPlugin>();
+
+    if let Err(err) = replay::setup(&mut app, mode) {
+        eprintln!("{err}");
+        return;
+    }
+
+    app.run().await;
+
+    // Quitting mid-game keeps the recording too
+    if let Err(err) = replay::finish(app.storage()) {
+        eprintln!("{err}");
+    }
 }
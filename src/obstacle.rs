@@ -0,0 +1,110 @@
+use crate::{
+    cfg::{OBSTACLE_COLOR, OBSTACLE_SIZE},
+    game::Position,
+    game_over::{DeathCause, GameOver},
+    player::Player,
+    Rect, Shape,
+};
+use core::{storage::Component, Plugin, PluginBuilder, Storage};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+// Wall of the level, the snake dies when its head runs into one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Obstacle {
+    shape: Rect,
+    position: Position,
+}
+
+impl Plugin for Obstacle {
+    fn new(builder: &mut PluginBuilder) {
+        builder.register_component::<Obstacle>();
+        builder.add_system(Obstacle::draw);
+    }
+}
+
+// Methods
+impl Obstacle {
+    pub fn at(position: Position) -> Self {
+        Obstacle {
+            shape: Rect {
+                x: Position::compute(position.0),
+                y: Position::compute(position.1),
+                width: OBSTACLE_SIZE,
+                height: OBSTACLE_SIZE,
+                color: OBSTACLE_COLOR,
+            },
+            position,
+        }
+    }
+
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+}
+
+// Systmes
+impl Obstacle {
+    pub fn collide(storage: &mut Storage) {
+        let Some(head) = storage.get_first::<Player>().map(Player::position) else {
+            return;
+        };
+
+        let hit: bool = storage
+            .get_all::<Obstacle>()
+            .is_some_and(|obstacles| obstacles.iter().any(|obstacle| obstacle.position == head));
+
+        if hit {
+            GameOver::die(storage, DeathCause::Obstacle);
+        }
+    }
+
+    pub fn draw(storage: &mut Storage) {
+        if let Some(obstacles) = storage.get_all::<Obstacle>() {
+            obstacles.iter().for_each(|obstacle| obstacle.shape.draw());
+        }
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Obstacle {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::Position,
+        game_over::{DeathCause, GameState},
+        level::Level,
+        player::Player,
+        testing::headless_app,
+    };
+    use core::{App, ScriptedInput};
+
+    #[test]
+    fn hitting_a_wall_ends_the_game() {
+        let mut app: App = headless_app(ScriptedInput::new());
+        app.storage_mut().add(">..#".parse::<Level>().unwrap());
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert_eq!(snake.position(), Position(3, 0));
+
+        assert!(matches!(
+            app.storage().get_first::<GameState>(),
+            Some(GameState::Over(death)) if death.cause == DeathCause::Obstacle
+        ));
+    }
+}
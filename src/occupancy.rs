@@ -0,0 +1,220 @@
+use rand::Rng;
+use std::any::Any;
+
+use crate::{board::Board, food::Food, game::Position, obstacle::Obstacle, player::Player};
+use core::{storage::Component, Plugin, PluginBuilder, Storage};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Free,
+    Snake,
+    Food,
+    Obstacle,
+}
+
+// What takes every cell of the board, rebuilt once per update
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occupancy {
+    board: Board,
+    cells: Vec<Cell>,
+    free: usize,
+}
+
+impl Plugin for Occupancy {
+    fn new(builder: &mut PluginBuilder) {
+        builder.add_system(Occupancy::update);
+    }
+}
+
+// Methods
+impl Occupancy {
+    pub fn new(board: Board) -> Self {
+        let count: usize = (board.width.max(0) * board.height.max(0)) as usize;
+
+        Self {
+            board,
+            cells: vec![Cell::Free; count],
+            free: count,
+        }
+    }
+
+    fn index(&self, position: Position) -> Option<usize> {
+        self.board
+            .contains(position)
+            .then(|| (position.1 * self.board.width + position.0) as usize)
+    }
+
+    /// None outside of the board
+    pub fn get(&self, position: Position) -> Option<Cell> {
+        self.index(position).map(|index| self.cells[index])
+    }
+
+    /// Positions outside of the board are ignored
+    pub fn set(&mut self, position: Position, cell: Cell) {
+        let Some(index) = self.index(position) else {
+            return;
+        };
+
+        match (self.cells[index], cell) {
+            (Cell::Free, Cell::Free) => {}
+            (Cell::Free, _) => self.free -= 1,
+            (_, Cell::Free) => self.free += 1,
+            _ => {}
+        }
+
+        self.cells[index] = cell;
+    }
+
+    pub fn is_free(&self, position: Position) -> bool {
+        self.get(position) == Some(Cell::Free)
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.free == 0
+    }
+
+    pub fn free_cells(&self) -> impl Iterator<Item = Position> + '_ {
+        let width: usize = self.board.width as usize;
+
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| **cell == Cell::Free)
+            .map(move |(index, _)| Position((index % width) as i32, (index / width) as i32))
+    }
+
+    /// Every free cell is equally likely, none when the board is full
+    pub fn rand_free<R: Rng>(&self, rng: &mut R) -> Option<Position> {
+        if self.is_full() {
+            return None;
+        }
+
+        self.free_cells().nth(rng.gen_range(0..self.free))
+    }
+}
+
+// Systems
+impl Occupancy {
+    pub fn update(storage: &mut Storage) {
+        let mut occupancy: Occupancy = Occupancy::new(Board::of(storage));
+
+        if let Some(obstacles) = storage.get_all::<Obstacle>() {
+            for obstacle in obstacles {
+                occupancy.set(*obstacle.position(), Cell::Obstacle);
+            }
+        }
+
+        if let Some(food) = storage.get_all::<Food>() {
+            for f in food {
+                occupancy.set(*f.position(), Cell::Food);
+            }
+        }
+
+        if let Some(snake) = storage.get_first::<Player>() {
+            for segment in snake.segments() {
+                occupancy.set(segment, Cell::Snake);
+            }
+        }
+
+        match storage.get_first_mut::<Occupancy>() {
+            Some(current) => *current = occupancy,
+            None => {
+                storage.add(occupancy);
+            }
+        }
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Occupancy {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cell, Occupancy};
+    use crate::{board::Board, game::Position};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn board(width: i32, height: i32) -> Board {
+        Board {
+            width,
+            height,
+            ..Board::default()
+        }
+    }
+
+    #[test]
+    fn free_cells_are_counted() {
+        let mut occupancy: Occupancy = Occupancy::new(board(3, 2));
+
+        occupancy.set(Position(0, 0), Cell::Snake);
+        occupancy.set(Position(0, 0), Cell::Food);
+        occupancy.set(Position(2, 1), Cell::Obstacle);
+        // Outside of the board
+        occupancy.set(Position(3, 0), Cell::Snake);
+
+        assert_eq!(occupancy.free_count(), 4);
+        assert_eq!(
+            occupancy.free_cells().collect::<Vec<_>>(),
+            [
+                Position(1, 0),
+                Position(2, 0),
+                Position(0, 1),
+                Position(1, 1)
+            ]
+        );
+
+        occupancy.set(Position(0, 0), Cell::Free);
+
+        assert_eq!(occupancy.free_count(), 5);
+        assert_eq!(occupancy.get(Position(3, 0)), None);
+    }
+
+    #[test]
+    fn random_cell_is_always_free() {
+        let mut occupancy: Occupancy = Occupancy::new(board(4, 4));
+        let mut rng: StdRng = StdRng::seed_from_u64(0);
+
+        for x in 0..4 {
+            occupancy.set(Position(x, 1), Cell::Obstacle);
+        }
+
+        let mut seen: Vec<Position> = Vec::new();
+
+        for _ in 0..256 {
+            let position: Position = occupancy.rand_free(&mut rng).unwrap();
+
+            assert!(occupancy.is_free(position));
+
+            if !seen.contains(&position) {
+                seen.push(position);
+            }
+        }
+
+        assert_eq!(seen.len(), 12);
+    }
+
+    #[test]
+    fn full_board_has_no_free_cell() {
+        let mut occupancy: Occupancy = Occupancy::new(board(2, 1));
+
+        occupancy.set(Position(0, 0), Cell::Snake);
+        occupancy.set(Position(1, 0), Cell::Snake);
+
+        assert!(occupancy.is_full());
+        assert_eq!(occupancy.rand_free(&mut StdRng::seed_from_u64(0)), None);
+    }
+}
@@ -1,33 +1,46 @@
-use macroquad::prelude::{is_key_pressed, KeyCode};
-
 use crate::{
-    cfg::{CELL_COUNT, GAME_OVER, SNAKE_COLOR, SNAKE_SIZE, SNAKE_STEP_INTERVAL, SNAKE_X, SNAKE_Y},
-    food::Food,
+    board::Board,
+    cfg::{SNAKE_COLOR, SNAKE_SIZE, SNAKE_STEP_INTERVAL, SNAKE_TURN_BUFFER, SNAKE_X, SNAKE_Y},
+    food::{Effect, Food, FoodKind},
     game::Position,
+    game_over::{DeathCause, GameOver, GameState, Score, Simulation},
+    input::{Action, ActionState},
+    obstacle::Obstacle,
     Rect, Shape,
 };
-use core::{storage::Component, Plugin, PluginBuilder, Storage};
-use std::any::Any;
+use core::{
+    storage::Component, system::SystemId, Plugin, PluginBuilder, Storage, Time, Timer, TimerMode,
+    UniquePolicy,
+};
+use serde::{Deserialize, Serialize};
+use std::{any::Any, collections::VecDeque, iter, time::Duration};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Player {
     head: Rect,
     position: Position,
     direction: Direction,
     tail: Vec<Position>,
+    #[serde(skip)]
+    turns: TurnQueue,
+    #[serde(skip)]
+    pace: Pace,
 }
 
 impl Plugin for Player {
     fn new(builder: &mut PluginBuilder) {
         builder
-            .add_startup_system(Self::init)
-            .add_system(Self::translate_position)
-            .add_system(Self::draw)
-            .add_system(Self::controls)
-            .add_system(Self::out_bounds)
-            .add_interval_system(Self::eat, SNAKE_STEP_INTERVAL)
-            .add_interval_system(Self::moving_at_grid, SNAKE_STEP_INTERVAL)
-            .add_interval_system(Self::cannibalism, SNAKE_STEP_INTERVAL);
+            .register_component::<Self>()
+            .register_unique::<Self>(UniquePolicy::Error);
+        builder.add_system(Self::translate_position);
+        builder.add_system(Self::draw);
+
+        let simulation: Vec<SystemId> = vec![
+            builder.add_system(Self::controls),
+            builder.add_system(Self::step),
+        ];
+        Simulation::add(builder, simulation);
     }
 }
 
@@ -44,61 +57,112 @@ impl Default for Player {
             position: Position(SNAKE_X, SNAKE_Y),
             direction: Direction::None,
             tail: Vec::new(),
+            turns: TurnQueue::default(),
+            pace: Pace::default(),
         }
     }
 }
 
-// Systmes
+// Methods
 impl Player {
-    pub fn init(storage: &mut Storage) {
-        storage.add(Self::default());
+    pub fn at(position: Position, direction: Direction) -> Self {
+        Player {
+            position,
+            direction,
+            ..Player::default()
+        }
     }
 
-    pub fn draw(storage: &mut Storage) {
-        if let Some(snake) = storage.get_first_mut::<Player>() {
-            // Draw head
-            snake.head.draw();
-
-            // Draw tail
-            snake.tail.iter().for_each(|segment| {
-                Rect::draw(
-                    Position::compute(segment.0),
-                    Position::compute(segment.1),
-                    snake.head.width,
-                    snake.head.height,
-                    snake.head.color,
-                )
-            });
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Head and tail segments
+    pub fn length(&self) -> usize {
+        self.tail.len() + 1
+    }
+
+    /// Head followed by the tail
+    pub fn segments(&self) -> impl Iterator<Item = Position> + '_ {
+        iter::once(self.position).chain(self.tail.iter().copied())
+    }
+
+    pub fn pace(&self) -> &Pace {
+        &self.pace
+    }
+
+    /// Effect of eaten food
+    pub fn apply(&mut self, effect: Effect) {
+        match effect {
+            Effect::Grow(segments) => {
+                for _ in 0..segments {
+                    let segment: Position = match self.tail.len() {
+                        0 => self.position,
+                        len => self.tail[len - 1],
+                    };
+
+                    self.tail.push(segment);
+                }
+            }
+            Effect::Shrink(segments) => {
+                self.tail.truncate(self.tail.len().saturating_sub(segments));
+            }
+            Effect::Speed { factor, seconds } => {
+                self.pace.boost(factor, Duration::from_secs_f32(seconds));
+            }
         }
     }
+}
+
+// Systmes
+impl Player {
+    pub fn draw(storage: &mut Storage) {
+        // No snake until a level is loaded
+        let Some(snake) = storage.get_first::<Player>() else {
+            return;
+        };
+
+        // Draw head
+        snake.head.draw();
+
+        // Draw tail
+        snake.tail.iter().for_each(|segment| {
+            Rect::draw(
+                Position::compute(segment.0),
+                Position::compute(segment.1),
+                snake.head.width,
+                snake.head.height,
+                snake.head.color,
+            )
+        });
+    }
 
     pub fn moving_at_grid(storage: &mut Storage) {
-        if let Some(snake) = storage.get_first_mut::<Player>() {
-            // Move tail
-            if snake.tail.len() > 0 {
-                let Position(mut x, mut y) = snake.position;
-
-                snake.tail.iter_mut().for_each(|segment| {
-                    (x, segment.0) = (segment.0, x);
-                    (y, segment.1) = (segment.1, y);
-                });
-            }
+        let board: Board = Board::of(storage);
+        let Some(snake) = storage.get_first_mut::<Player>() else {
+            return;
+        };
 
-            // Move head
-            {
-                snake.position.0 += match snake.direction {
-                    Direction::Left => -1,
-                    Direction::Right => 1,
-                    _ => 0,
-                };
-
-                snake.position.1 += match snake.direction {
-                    Direction::Top => -1,
-                    Direction::Down => 1,
-                    _ => 0,
-                };
-            }
+        // One queued turn per step
+        let applied: Direction = snake.direction;
+        snake.direction = snake.turns.next(applied);
+
+        // Move tail
+        if !snake.tail.is_empty() {
+            let Position(mut x, mut y) = snake.position;
+
+            snake.tail.iter_mut().for_each(|segment| {
+                (x, segment.0) = (segment.0, x);
+                (y, segment.1) = (segment.1, y);
+            });
         }
+
+        // Move head, wrapped on toroidal boards
+        snake.position = board.step(snake.position, snake.direction);
     }
 
     pub fn translate_position(storage: &mut Storage) {
@@ -109,21 +173,61 @@ impl Player {
     }
 
     pub fn controls(storage: &mut Storage) {
-        if let Some(snake) = storage.get_first_mut::<Player>() {
-            if is_key_pressed(KeyCode::W) && snake.direction != Direction::Down {
-                snake.direction = Direction::Top;
-            } else if is_key_pressed(KeyCode::A) && snake.direction != Direction::Right {
-                snake.direction = Direction::Left;
-            } else if is_key_pressed(KeyCode::S) && snake.direction != Direction::Top {
-                snake.direction = Direction::Down;
-            } else if is_key_pressed(KeyCode::D) && snake.direction != Direction::Left {
-                snake.direction = Direction::Right;
+        let Some(actions) = storage
+            .get_first::<ActionState>()
+            .map(|state| state.player(0))
+        else {
+            return;
+        };
+
+        let pressed: Option<Direction> = if actions.just_pressed(Action::MoveUp) {
+            Some(Direction::Top)
+        } else if actions.just_pressed(Action::MoveLeft) {
+            Some(Direction::Left)
+        } else if actions.just_pressed(Action::MoveDown) {
+            Some(Direction::Down)
+        } else if actions.just_pressed(Action::MoveRight) {
+            Some(Direction::Right)
+        } else {
+            None
+        };
+
+        if let (Some(direction), Some(snake)) = (pressed, storage.get_first_mut::<Player>()) {
+            snake.turns.push(direction);
+        }
+    }
+
+    /// Runs the movement once per step, several times when the snake is fast
+    pub fn step(storage: &mut Storage) {
+        let delta: Duration = storage
+            .get_first::<Time>()
+            .map_or(Duration::ZERO, Time::delta);
+        let Some(steps) = storage
+            .get_first_mut::<Player>()
+            .map(|snake| snake.pace.tick(delta))
+        else {
+            return;
+        };
+
+        for _ in 0..steps {
+            Self::eat(storage);
+            Self::moving_at_grid(storage);
+            // Checked after every step, fast snakes would jump over walls
+            Self::out_bounds(storage);
+            Obstacle::collide(storage);
+            Self::cannibalism(storage);
+
+            if storage
+                .get_first::<GameState>()
+                .is_some_and(|state| *state != GameState::Playing)
+            {
+                break;
             }
         }
     }
 
     pub fn eat(storage: &mut Storage) {
-        let mut can_grow = false;
+        let mut eaten: Option<FoodKind> = None;
 
         // Eat
         if let Some((snake, food)) = storage.get_several_mut::<Player, Food>() {
@@ -137,47 +241,43 @@ impl Player {
             }
 
             if let Some(idx) = index {
+                eaten = Some(food[idx].kind().clone());
                 storage.remove::<Food>(idx);
-
-                can_grow = true;
             }
         }
 
-        // Snake growth
-        if can_grow {
-            if let Some(snake) = storage.get_first_mut::<Player>() {
-                let segment: Position = match snake.tail.len() {
-                    0 => snake.position.clone(),
-                    len => snake.tail[len - 1].clone(),
-                };
+        // Score and effect of the food
+        if let Some(kind) = eaten {
+            if let Some(score) = storage.get_first_mut::<Score>() {
+                score.0 += kind.score;
+            }
 
-                snake.tail.push(segment);
+            if let Some(snake) = storage.get_first_mut::<Player>() {
+                snake.apply(kind.effect);
             }
         }
     }
 
     pub fn out_bounds(storage: &mut Storage) {
+        let board: Board = Board::of(storage);
+
         if let Some(snake) = storage.get_first::<Player>() {
-            if (snake.position.0 < 0 || snake.position.0 > (CELL_COUNT - 1_u8).into())
-                || (snake.position.1 < 0 || snake.position.1 > (CELL_COUNT - 1_u8).into())
-            {
-                panic!("{GAME_OVER}\nYour snake is out of bounds");
+            if !board.contains(snake.position) {
+                GameOver::die(storage, DeathCause::OutOfBounds);
             }
         }
     }
 
     pub fn cannibalism(storage: &mut Storage) {
         if let Some(snake) = storage.get_first::<Player>() {
-            snake.tail.iter().for_each(|segment| {
-                if *segment == snake.position {
-                    panic!("{GAME_OVER}\nYour snake has eaten its tail")
-                }
-            })
+            if snake.tail.contains(&snake.position) {
+                GameOver::die(storage, DeathCause::Cannibalism);
+            }
         }
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum Direction {
     Top,
     Down,
@@ -186,6 +286,144 @@ pub enum Direction {
     None,
 }
 
+impl Direction {
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Top => Direction::Down,
+            Direction::Down => Direction::Top,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::None => Direction::None,
+        }
+    }
+
+    /// Change of the position after one step
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Top => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+            Direction::None => (0, 0),
+        }
+    }
+}
+
+// Time between steps, food may change it for a while
+#[derive(Debug, Clone)]
+pub struct Pace {
+    interval: Duration,
+    step: Timer,
+    factor: f32,
+    boost: Option<Timer>,
+}
+
+impl Pace {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            step: Timer::new(interval, TimerMode::Repeating),
+            factor: 1.,
+            boost: None,
+        }
+    }
+
+    /// Steps due after the delta
+    pub fn tick(&mut self, delta: Duration) -> u32 {
+        if let Some(boost) = self.boost.as_mut() {
+            if boost.tick(delta).finished() {
+                self.boost = None;
+                self.factor = 1.;
+            }
+        }
+
+        // Whole nanoseconds, floats would make even steps drift
+        let nanos: f64 = self.interval.as_nanos() as f64 / self.factor as f64;
+        self.step
+            .set_duration(Duration::from_nanos(nanos.round() as u64));
+        self.step.tick(delta).times_finished()
+    }
+
+    /// Multiplies the steps per second, replaces the previous boost
+    pub fn boost(&mut self, factor: f32, duration: Duration) {
+        if factor <= 0. || !factor.is_finite() {
+            return;
+        }
+
+        self.factor = factor;
+        self.boost = Some(Timer::new(duration, TimerMode::Once));
+    }
+
+    pub fn factor(&self) -> f32 {
+        self.factor
+    }
+}
+
+impl Default for Pace {
+    fn default() -> Self {
+        Self::new(SNAKE_STEP_INTERVAL)
+    }
+}
+
+// Turns pressed between two steps, validated when they are applied
+#[derive(Debug, Clone)]
+pub struct TurnQueue {
+    turns: VecDeque<Direction>,
+    capacity: usize,
+}
+
+impl TurnQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            turns: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns false if the turn repeats the previous one or the queue is full
+    pub fn push(&mut self, direction: Direction) -> bool {
+        if direction == Direction::None
+            || self.turns.back() == Some(&direction)
+            || self.turns.len() >= self.capacity
+        {
+            return false;
+        }
+
+        self.turns.push_back(direction);
+        true
+    }
+
+    /// Takes the first turn valid from the applied direction, the rest stay queued
+    pub fn next(&mut self, applied: Direction) -> Direction {
+        while let Some(turn) = self.turns.pop_front() {
+            // Reversing would run into the neck
+            if turn != applied && turn != applied.opposite() {
+                return turn;
+            }
+        }
+
+        applied
+    }
+
+    pub fn len(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.turns.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+}
+
+impl Default for TurnQueue {
+    fn default() -> Self {
+        Self::new(SNAKE_TURN_BUFFER)
+    }
+}
+
 // TODO: Clean up this crap after adding macros to the repository...
 
 impl Component for Player {
@@ -197,3 +435,221 @@ impl Component for Player {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Pace, Player, TurnQueue};
+    use crate::{
+        board::{Board, Topology},
+        cfg::CELL_COUNT,
+        food::Effect,
+        game::Position,
+        game_over::GameState,
+        level::{Level, LevelFile},
+        testing::{game_without_window, headless_app},
+    };
+    use core::{App, ScriptedInput};
+    use macroquad::prelude::KeyCode;
+    use std::time::Duration;
+
+    fn steps(queue: &mut TurnQueue, mut applied: Direction, count: usize) -> Vec<Direction> {
+        (0..count)
+            .map(|_| {
+                applied = queue.next(applied);
+                applied
+            })
+            .collect()
+    }
+
+    #[test]
+    fn turns_pressed_within_one_step_are_kept() {
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        queue.push(Direction::Top);
+        queue.push(Direction::Left);
+
+        assert_eq!(
+            steps(&mut queue, Direction::Right, 3),
+            [Direction::Top, Direction::Left, Direction::Left]
+        );
+    }
+
+    #[test]
+    fn reversal_is_checked_against_the_applied_direction() {
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        // Moving right, up then left would reverse if checked against the pressed keys only
+        queue.push(Direction::Top);
+        queue.push(Direction::Left);
+        assert_eq!(queue.next(Direction::Right), Direction::Top);
+
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        // A quick left while moving right is dropped
+        queue.push(Direction::Left);
+        assert_eq!(queue.next(Direction::Right), Direction::Right);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn invalid_turns_are_skipped_in_the_same_step() {
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        queue.push(Direction::Left);
+        queue.push(Direction::Down);
+
+        assert_eq!(queue.next(Direction::Right), Direction::Down);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn repeated_presses_are_recorded_once() {
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        assert!(queue.push(Direction::Top));
+        assert!(!queue.push(Direction::Top));
+        assert!(!queue.push(Direction::None));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn buffer_is_capped() {
+        let mut queue: TurnQueue = TurnQueue::new(2);
+
+        assert!(queue.push(Direction::Top));
+        assert!(queue.push(Direction::Left));
+        assert!(!queue.push(Direction::Down));
+
+        assert_eq!(
+            steps(&mut queue, Direction::Right, 3),
+            [Direction::Top, Direction::Left, Direction::Left]
+        );
+    }
+
+    #[test]
+    fn first_turn_from_standing_still() {
+        let mut queue: TurnQueue = TurnQueue::new(3);
+
+        queue.push(Direction::Down);
+        queue.push(Direction::Top);
+
+        // Top reverses the applied down
+        assert_eq!(
+            steps(&mut queue, Direction::None, 2),
+            [Direction::Down, Direction::Down]
+        );
+    }
+
+    #[test]
+    fn boost_changes_the_steps_for_a_while() {
+        let mut pace: Pace = Pace::new(Duration::from_millis(100));
+
+        assert_eq!(pace.tick(Duration::from_millis(100)), 1);
+
+        pace.boost(2., Duration::from_millis(300));
+
+        assert_eq!(pace.tick(Duration::from_millis(100)), 2);
+        assert_eq!(pace.tick(Duration::from_millis(100)), 2);
+        assert_eq!(pace.tick(Duration::from_millis(100)), 1);
+        assert_eq!(pace.factor(), 1.);
+
+        pace.boost(0.5, Duration::from_secs(1));
+
+        assert_eq!(pace.tick(Duration::from_millis(100)), 0);
+        assert_eq!(pace.tick(Duration::from_millis(100)), 1);
+    }
+
+    #[test]
+    fn poison_shrinks_the_tail_but_keeps_the_head() {
+        let mut snake: Player = Player::default();
+
+        snake.apply(Effect::Grow(3));
+        snake.apply(Effect::Shrink(2));
+
+        assert_eq!(snake.length(), 2);
+
+        snake.apply(Effect::Shrink(2));
+
+        assert_eq!(snake.length(), 1);
+    }
+
+    #[test]
+    fn scripted_press_turns_the_snake() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(3, KeyCode::D);
+
+        let mut app: App = headless_app(input);
+
+        for _ in 0..=5 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert!(!app.is_stopped());
+        assert_eq!(snake.direction, Direction::Right);
+        assert_eq!(snake.position, Position(3, 15));
+    }
+
+    #[test]
+    fn scripted_swipe_turns_the_snake() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.swipe(3, (100., 100.), (200., 100.));
+
+        let mut app: App = headless_app(input);
+
+        for _ in 0..=5 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert!(!app.is_stopped());
+        assert_eq!(snake.direction, Direction::Right);
+        assert_eq!(snake.position, Position(2, 15));
+    }
+
+    #[test]
+    fn snake_wraps_around_a_toroidal_board() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input.press(1, KeyCode::A);
+
+        let mut app: App = headless_app(input);
+        let file: LevelFile = app.storage().get_first::<LevelFile>().cloned().unwrap();
+        let mut level: Level = Level::load(file.0).unwrap();
+        level.board.topology = Topology::Toroidal;
+
+        app.storage_mut().add(level);
+
+        for _ in 0..5 {
+            app.update();
+        }
+
+        let snake: &Player = app.storage().get_first::<Player>().unwrap();
+
+        assert_eq!(app.storage().count::<Board>(), 1);
+        assert_eq!(
+            app.storage().get_first::<GameState>(),
+            Some(&GameState::Playing)
+        );
+        assert_eq!(snake.position, Position(CELL_COUNT as i32 - 4, 15));
+    }
+
+    #[test]
+    fn nothing_fails_before_a_level_is_loaded() {
+        let mut app: App = game_without_window();
+        app.set_input_backend(ScriptedInput::new());
+
+        // Drawn too, without a snake nothing reaches the window
+        for id in app.scheduler_mut().ids_of(Player::draw) {
+            app.scheduler_mut().enable(id);
+        }
+
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert!(!app.is_stopped());
+        assert_eq!(app.storage().count::<Player>(), 0);
+    }
+}
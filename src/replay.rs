@@ -0,0 +1,428 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    cfg::LEVEL_SCENE,
+    food::FoodTypes,
+    game::GameRng,
+    game_over::GameState,
+    input::{Key, Keybindings},
+    level::{Level, LevelError},
+};
+use core::{
+    scene::SceneError, storage::Component, App, InputBackend, InputEvent, MacroquadInput,
+    PointerEvent, Recording, RecordingInput, ScriptedInput, Storage, Time,
+};
+
+// Bumped on every incompatible change of the format
+pub const REPLAY_VERSION: u32 = 2;
+
+// Everything needed to play the same session again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub seed: u64,
+    // Measured time of every update, played back at the speed it was recorded
+    pub deltas: Vec<Duration>,
+    // Source text of the level, the level file may change after recording
+    pub level: String,
+    pub keybindings: Keybindings,
+    pub food: FoodTypes,
+    pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub tick: u64,
+    pub input: RecordedInput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RecordedInput {
+    Pressed(Key),
+    Released(Key),
+    Pointer(PointerEvent),
+}
+
+impl From<InputEvent> for RecordedInput {
+    fn from(event: InputEvent) -> Self {
+        match event {
+            InputEvent::Pressed(key) => RecordedInput::Pressed(Key(key)),
+            InputEvent::Released(key) => RecordedInput::Released(Key(key)),
+            InputEvent::Pointer(event) => RecordedInput::Pointer(event),
+        }
+    }
+}
+
+impl From<RecordedInput> for InputEvent {
+    fn from(input: RecordedInput) -> Self {
+        match input {
+            RecordedInput::Pressed(key) => InputEvent::Pressed(key.0),
+            RecordedInput::Released(key) => InputEvent::Released(key.0),
+            RecordedInput::Pointer(event) => InputEvent::Pointer(event),
+        }
+    }
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let data: String = fs::read_to_string(path)?;
+        let replay: Replay = serde_json::from_str(&data)?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::Version(replay.version));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let data: String = serde_json::to_string_pretty(self)?;
+
+        Ok(fs::write(path, data)?)
+    }
+
+    pub fn input(&self) -> ScriptedInput {
+        let mut input: ScriptedInput = ScriptedInput::new();
+
+        for event in self.events.iter() {
+            input.event(event.tick, event.input.into());
+        }
+
+        input
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mode {
+    Play,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Mode {
+    /// `--record <file>` or `--replay <file>`, nothing to just play
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mode: Mode = match args.next().as_deref() {
+            None => return Ok(Mode::Play),
+            Some("--record") => Mode::Record(Self::path(args.next())?),
+            Some("--replay") => Mode::Replay(Self::path(args.next())?),
+            Some(arg) => return Err(format!("unknown argument `{arg}`")),
+        };
+
+        match args.next() {
+            Some(arg) => Err(format!("unexpected argument `{arg}`")),
+            None => Ok(mode),
+        }
+    }
+
+    fn path(arg: Option<String>) -> Result<PathBuf, String> {
+        arg.map(PathBuf::from)
+            .ok_or_else(|| "missing replay file".to_string())
+    }
+}
+
+/// Sets up the level, randomness, time and input of the mode
+pub fn setup(app: &mut App, mode: Mode) -> Result<(), ReplayError> {
+    let seed: u64 = match &mode {
+        Mode::Replay(path) => {
+            let replay: Replay = Replay::load(path)?;
+
+            app.set_input_backend(replay.input())
+                .storage_mut()
+                .add(replay.level.parse::<Level>()?)
+                .add(replay.keybindings)
+                .add(replay.food);
+
+            if let Some(time) = app.storage_mut().get_first_mut::<Time>() {
+                time.play_back(replay.deltas);
+            }
+
+            replay.seed
+        }
+        Mode::Record(path) => {
+            app.load_scene(LEVEL_SCENE)?;
+            record(app, path.clone(), MacroquadInput::default())
+        }
+        Mode::Play => {
+            app.load_scene(LEVEL_SCENE)?;
+            rand::random()
+        }
+    };
+
+    app.storage_mut().add(GameRng::new(seed));
+    Ok(())
+}
+
+/// Records the input of the backend and the time of every update, returns the seed to play with
+pub fn record<B: InputBackend>(app: &mut App, path: PathBuf, backend: B) -> u64 {
+    let recording: Recording = Arc::new(Mutex::new(Vec::new()));
+    let seed: u64 = rand::random();
+
+    app.set_input_backend(RecordingInput::new(backend, recording.clone()));
+    app.add_system(record_delta);
+    app.add_system(save_recording);
+    app.storage_mut().add(Recorder {
+        path,
+        seed,
+        recording,
+        deltas: Vec::new(),
+        saved: false,
+    });
+
+    seed
+}
+
+// Keeps the input in memory, written when a game ends and when the app stops
+#[derive(Debug)]
+pub struct Recorder {
+    path: PathBuf,
+    seed: u64,
+    recording: Recording,
+    deltas: Vec<Duration>,
+    // Written since the game has ended
+    saved: bool,
+}
+
+impl Recorder {
+    fn replay(&self, storage: &Storage) -> Replay {
+        let events: Vec<RecordedEvent> = match self.recording.lock() {
+            Ok(recording) => recording
+                .iter()
+                .map(|(tick, event)| RecordedEvent {
+                    tick: *tick,
+                    input: (*event).into(),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        Replay {
+            version: REPLAY_VERSION,
+            seed: self.seed,
+            deltas: self.deltas.clone(),
+            level: storage
+                .get_first::<Level>()
+                .map_or(String::new(), |level| level.source().to_string()),
+            keybindings: storage
+                .get_first::<Keybindings>()
+                .cloned()
+                .unwrap_or_default(),
+            food: storage
+                .get_first::<FoodTypes>()
+                .cloned()
+                .unwrap_or_default(),
+            events,
+        }
+    }
+}
+
+// Input is recorded by ticks, the time has to match them one to one
+pub fn record_delta(storage: &mut Storage) {
+    let Some(delta) = storage.get_first::<Time>().map(Time::delta) else {
+        return;
+    };
+
+    if let Some(recorder) = storage.get_first_mut::<Recorder>() {
+        recorder.deltas.push(delta);
+    }
+}
+
+/// Writes the recording once whenever a game ends
+pub fn save_recording(storage: &mut Storage) -> Result<(), ReplayError> {
+    let playing: bool = matches!(
+        storage.get_first::<GameState>(),
+        None | Some(GameState::Playing)
+    );
+
+    let Some(recorder) = storage.get_first_mut::<Recorder>() else {
+        return Ok(());
+    };
+
+    if playing {
+        // Written again when the next game ends
+        recorder.saved = false;
+        return Ok(());
+    }
+
+    if recorder.saved {
+        return Ok(());
+    }
+
+    recorder.saved = true;
+    finish(storage)
+}
+
+/// Writes the whole recording, nothing without one
+pub fn finish(storage: &Storage) -> Result<(), ReplayError> {
+    match storage.get_first::<Recorder>() {
+        Some(recorder) => recorder.replay(storage).save(&recorder.path),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    Version(u32),
+    Level(LevelError),
+    Scene(SceneError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{err}"),
+            ReplayError::Format(err) => write!(f, "invalid replay: {err}"),
+            ReplayError::Version(version) => write!(
+                f,
+                "replay version {version} is not supported, expected {REPLAY_VERSION}"
+            ),
+            ReplayError::Level(err) => write!(f, "{err}"),
+            ReplayError::Scene(err) => write!(f, "failed to load scene `{LEVEL_SCENE}`: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ReplayError {
+    fn from(err: serde_json::Error) -> Self {
+        ReplayError::Format(err)
+    }
+}
+
+impl From<LevelError> for ReplayError {
+    fn from(err: LevelError) -> Self {
+        ReplayError::Level(err)
+    }
+}
+
+impl From<SceneError> for ReplayError {
+    fn from(err: SceneError) -> Self {
+        ReplayError::Scene(err)
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Recorder {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{finish, record, setup, Mode, Replay};
+    use crate::{
+        food::Food,
+        game::{GameRng, Position},
+        game_over::{GameState, Score},
+        level::Level,
+        player::Player,
+        testing::{game_without_window, headless_app},
+    };
+    use core::{App, ScriptedInput, Time};
+    use macroquad::prelude::KeyCode;
+    use std::{fs, path::PathBuf, time::Duration};
+
+    // Everything a replay has to reproduce after an update
+    #[derive(Debug, PartialEq)]
+    struct Frame {
+        food: Vec<Position>,
+        snake: Vec<Position>,
+        score: Option<Score>,
+        state: Option<GameState>,
+    }
+
+    fn frames(app: &mut App, count: usize) -> Vec<Frame> {
+        (0..count)
+            .map(|_| {
+                app.update();
+
+                let storage = app.storage();
+
+                Frame {
+                    food: storage.get_all::<Food>().map_or(Vec::new(), |food| {
+                        food.iter().map(|f| *f.position()).collect()
+                    }),
+                    snake: storage
+                        .get_first::<Player>()
+                        .map_or(Vec::new(), |snake| snake.segments().collect()),
+                    score: storage.get_first::<Score>().copied(),
+                    state: storage.get_first::<GameState>().cloned(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replay_reproduces_the_recorded_game() {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("replay-{}.json", std::process::id()));
+        let mut input: ScriptedInput = ScriptedInput::new();
+        // The settings pause the game for a while
+        input.press(10, KeyCode::Escape).press(14, KeyCode::Escape);
+
+        // Every cell is reachable, the snake eats whatever spawns
+        let level: Level = "topology: toroidal\n---\n>.......".parse().unwrap();
+
+        let mut recorded: App = headless_app(ScriptedInput::new());
+        let seed: u64 = record(&mut recorded, path.clone(), input);
+        recorded.storage_mut().add(GameRng::new(seed)).add(level);
+
+        // Uneven frames, the replay has to run at the same speed
+        if let Some(time) = recorded.storage_mut().get_first_mut::<Time>() {
+            time.set_step(None);
+            time.play_back(
+                [60, 125, 190]
+                    .into_iter()
+                    .cycle()
+                    .take(40)
+                    .map(Duration::from_millis),
+            );
+        }
+
+        let expected: Vec<Frame> = frames(&mut recorded, 40);
+
+        finish(recorded.storage()).unwrap();
+
+        let replay: Replay = Replay::load(&path).unwrap();
+
+        assert_eq!(replay.seed, seed);
+        assert_eq!(replay.deltas.len(), 40);
+        assert_eq!(replay.deltas[1], Duration::from_millis(125));
+
+        let mut replayed: App = game_without_window();
+        setup(&mut replayed, Mode::Replay(path.clone())).unwrap();
+
+        let actual: Vec<Frame> = frames(&mut replayed, 40);
+
+        fs::remove_file(path).unwrap();
+
+        // Food spawned at random cells until the snake filled the board
+        assert!(matches!(
+            expected.last().and_then(|frame| frame.state.as_ref()),
+            Some(GameState::Won(_))
+        ));
+        assert_eq!(actual, expected);
+    }
+}
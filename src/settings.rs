@@ -0,0 +1,212 @@
+use std::any::Any;
+use tracing::info;
+
+use crate::{
+    cfg::SETTINGS,
+    game_over::{draw_overlay, GameState, Simulation},
+    input::{Action, ActionState, Actions, Keybindings, Rebind},
+};
+use core::{storage::Component, Plugin, PluginBuilder, Storage};
+
+// Actions listed by the menu, in order
+const ACTIONS: [Action; 6] = [
+    Action::MoveUp,
+    Action::MoveDown,
+    Action::MoveLeft,
+    Action::MoveRight,
+    Action::Pause,
+    Action::Confirm,
+];
+
+// Keys of the first player, opened with pause while playing.
+// Confirm waits for the next key and binds it to the selected action
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Settings {
+    selected: usize,
+    // Waiting for the key of the selected action
+    capturing: bool,
+}
+
+impl Plugin for Settings {
+    fn new(builder: &mut PluginBuilder) {
+        builder.add_system(Settings::controls);
+        builder.add_system(Settings::draw);
+    }
+}
+
+// Methods
+impl Settings {
+    pub fn selected(&self) -> Action {
+        ACTIONS[self.selected]
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    pub fn open(storage: &mut Storage) {
+        storage.add(Settings::default());
+        Simulation::set_running(storage, false);
+        info!("settings opened");
+    }
+
+    pub fn close(storage: &mut Storage) {
+        storage.remove::<Settings>(0);
+        Simulation::set_running(storage, true);
+        info!("settings closed");
+    }
+}
+
+// Systems
+impl Settings {
+    pub fn controls(storage: &mut Storage) {
+        let actions: Actions = match storage.get_first::<ActionState>() {
+            Some(state) => state.player(0).clone(),
+            None => return,
+        };
+        let rebinding: bool = storage.get_first::<Rebind>().is_some();
+        let playing: bool = storage.get_first::<GameState>() == Some(&GameState::Playing);
+
+        let Some(settings) = storage.get_first_mut::<Settings>() else {
+            if playing && actions.just_pressed(Action::Pause) {
+                Self::open(storage);
+            }
+
+            return;
+        };
+
+        // The captured key may be bound to an action already, skip it
+        if settings.capturing {
+            settings.capturing = rebinding;
+            return;
+        }
+
+        if actions.just_pressed(Action::MoveUp) {
+            settings.selected = (settings.selected + ACTIONS.len() - 1) % ACTIONS.len();
+        } else if actions.just_pressed(Action::MoveDown) {
+            settings.selected = (settings.selected + 1) % ACTIONS.len();
+        } else if actions.just_pressed(Action::Confirm) {
+            let action: Action = settings.selected();
+
+            settings.capturing = true;
+            storage.add(Rebind { player: 0, action });
+        } else if actions.just_pressed(Action::Pause) {
+            Self::close(storage);
+        }
+    }
+
+    pub fn draw(storage: &mut Storage) {
+        let Some(settings) = storage.get_first::<Settings>().copied() else {
+            return;
+        };
+
+        let keybindings: Keybindings = storage
+            .get_first::<Keybindings>()
+            .cloned()
+            .unwrap_or_default();
+        let keys = |action: Action| match keybindings.keys(0, action) {
+            [] => "-".to_string(),
+            keys => keys
+                .iter()
+                .map(|key| format!("{:?}", key.0))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        let mut lines: Vec<String> = vec![SETTINGS.to_string()];
+
+        lines.extend(ACTIONS.iter().enumerate().map(|(index, action)| {
+            match (index == settings.selected, settings.capturing) {
+                (true, true) => format!("> {action:?}: press a key"),
+                (true, false) => format!("> {action:?}: {}", keys(*action)),
+                (false, _) => format!("{action:?}: {}", keys(*action)),
+            }
+        }));
+        lines.push(format!(
+            "{} - rebind, {} - back",
+            keys(Action::Confirm),
+            keys(Action::Pause)
+        ));
+
+        draw_overlay(&lines);
+    }
+}
+
+// TODO: Clean up this crap after adding macros to the repository...
+
+impl Component for Settings {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+    use crate::{
+        cfg::APP_CONFIG,
+        game_over::{GameState, Simulation},
+        input::{Action, InputPlugin, Key, Keybindings, Rebind},
+    };
+    use core::{system::SystemId, App, ScriptedInput};
+    use macroquad::prelude::KeyCode;
+
+    fn step(_: &mut core::Storage) {}
+
+    #[test]
+    fn menu_rebinds_the_selected_action() {
+        let mut input: ScriptedInput = ScriptedInput::new();
+        input
+            .press(1, KeyCode::Escape)
+            .press(3, KeyCode::Down)
+            .press(5, KeyCode::Enter)
+            .press(7, KeyCode::K)
+            .press(9, KeyCode::Escape);
+
+        let mut app: App = App::new(APP_CONFIG);
+        app.add_plugin::<InputPlugin>()
+            .add_plugin::<Settings>()
+            .set_input_backend(input);
+
+        let draw: Vec<SystemId> = app.scheduler_mut().ids_of(Settings::draw);
+        let simulation: SystemId = app.add_system(step);
+        app.scheduler_mut().disable(draw[0]);
+        app.storage_mut()
+            .add(Keybindings::default())
+            .add(GameState::Playing)
+            .add(Simulation(vec![simulation]));
+
+        let mut opened: Option<Settings> = None;
+        let mut captured: bool = false;
+
+        for _ in 0..12 {
+            let was_open: bool = app.storage().count::<Settings>() > 0;
+
+            app.update();
+
+            opened = opened.or(app.storage().get_first::<Settings>().copied());
+            captured |= app.storage().count::<Rebind>() > 0;
+
+            // Commands of the previous update are applied by now
+            if was_open && app.storage().count::<Settings>() > 0 {
+                assert!(!app.scheduler_mut().is_enabled(simulation));
+            }
+        }
+
+        let keybindings: &Keybindings = app.storage().get_first::<Keybindings>().unwrap();
+
+        assert_eq!(
+            opened.map(|settings| settings.selected()),
+            Some(Action::MoveUp)
+        );
+        assert!(captured);
+        assert_eq!(keybindings.keys(0, Action::MoveDown), [Key(KeyCode::K)]);
+        // Closed again and the game goes on
+        assert_eq!(app.storage().count::<Settings>(), 0);
+        assert!(app.scheduler_mut().is_enabled(simulation));
+    }
+}
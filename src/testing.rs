@@ -0,0 +1,51 @@
+// Helpers shared by the tests of the game
+use crate::{
+    cfg::{APP_CONFIG, LEVEL_SCENE, SNAKE_STEP_INTERVAL},
+    food::{Food, FoodTypes},
+    game_over::GameOver,
+    obstacle::Obstacle,
+    player::Player,
+    settings::Settings,
+    Game,
+};
+use core::{system::SystemId, App, Scheduler, ScriptedInput, Time};
+
+// The game without drawing, nothing is loaded yet
+pub fn game_without_window() -> App {
+    let mut app: App = App::new(APP_CONFIG);
+    app.add_plugin::<Game>();
+
+    let scheduler: &mut Scheduler = app.scheduler_mut();
+    let draws: Vec<SystemId> = [
+        scheduler.ids_of(Player::draw),
+        scheduler.ids_of(Food::draw),
+        scheduler.ids_of(Obstacle::draw),
+        scheduler.ids_of(GameOver::draw),
+        scheduler.ids_of(Settings::draw),
+    ]
+    .concat();
+
+    for id in draws {
+        scheduler.disable(id);
+    }
+
+    app
+}
+
+// Every update is one movement step, without a window
+pub fn headless_app(input: ScriptedInput) -> App {
+    let mut app: App = game_without_window();
+
+    app.load_scene(LEVEL_SCENE)
+        .unwrap()
+        .set_input_backend(input);
+
+    if let Some(time) = app.storage_mut().get_first_mut::<Time>() {
+        time.set_step(Some(SNAKE_STEP_INTERVAL));
+    }
+
+    // Only normal food, random kinds would change the outcome
+    app.storage_mut().add(FoodTypes::default());
+
+    app
+}